@@ -0,0 +1,34 @@
+//! Reads packets from the input the way they are read from a socket.
+
+#![no_main]
+
+use async_std::task::block_on;
+use libfuzzer_sys::fuzz_target;
+use mc_nano::protocol::{
+    packets::{HandshakePacket, LoginStartPacket},
+    stream::{Framing, MemoryStream, MinecraftStream},
+};
+
+fuzz_target!(|data: &[u8]| {
+    block_on(async {
+        let mut stream = MemoryStream::new(data.to_vec());
+        while stream.read_frame(&Framing::default()).await.is_ok() {}
+
+        let mut stream = MemoryStream::new(data.to_vec());
+        let mut framing = Framing::default();
+        framing.set_compression(64);
+        while stream.read_frame(&framing).await.is_ok() {}
+
+        let mut stream = MemoryStream::new(data.to_vec());
+        let framing = Framing::limited(1024);
+        if stream
+            .read_packet_framed::<HandshakePacket>(&framing)
+            .await
+            .is_ok()
+        {
+            let _ = stream
+                .read_packet_framed::<LoginStartPacket>(&framing)
+                .await;
+        }
+    });
+});
@@ -0,0 +1,58 @@
+//! Runs everything a client can do before it is connected to the backend, with the input as
+//! what the client sent.
+//!
+//! The first byte picks whether maintenance is on. With it off, status requests try the backend,
+//! which isn't listening, so the offline response is built.
+
+#![no_main]
+
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
+
+use async_std::task::block_on;
+use libfuzzer_sys::fuzz_target;
+use mc_nano::{
+    config::Config,
+    protocol::{legacy::LEGACY_PING, stream::MemoryStream},
+    proxy::{pre_login, State},
+};
+
+fn config(maintenance: bool) -> Arc<Config> {
+    let mut config = Config {
+        target_ip: "127.0.0.1:1".to_owned(),
+        ..Config::default()
+    };
+    config.maintenance.enabled = maintenance;
+    // nothing should be limited, or most inputs would be dropped straight away
+    config.rate_limits.status.per_second = 0.0;
+    config.rate_limits.login.per_second = 0.0;
+    config.validate().unwrap();
+    Arc::new(config)
+}
+
+fuzz_target!(|data: &[u8]| {
+    static CONFIGS: OnceLock<[Arc<Config>; 2]> = OnceLock::new();
+    static STATE: OnceLock<State> = OnceLock::new();
+    let configs = CONFIGS.get_or_init(|| [config(false), config(true)]);
+    let state = STATE.get_or_init(State::new);
+
+    let Some((&flags, data)) = data.split_first() else {
+        return;
+    };
+    let config = &configs[(flags & 1) as usize];
+    let legacy = data.first() == Some(&LEGACY_PING);
+
+    let mut client = MemoryStream::new(data.to_vec());
+    let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let _ = block_on(pre_login(
+        &mut client,
+        ip,
+        legacy,
+        Instant::now(),
+        config,
+        state,
+    ));
+});
@@ -0,0 +1,67 @@
+//! Reads the input with one of the `MinecraftReadable` impls, picked by the first byte.
+
+#![no_main]
+
+use async_std::task::block_on;
+use libfuzzer_sys::fuzz_target;
+use mc_nano::protocol::{
+    legacy::read_legacy_ping,
+    packets::{
+        HandshakePacket, LoginDisconnectPacket, LoginStartPacket, NextState, PingRequestPacket,
+        PongResponsePacket, SetCompressionPacket, StatusRequestPacket, StatusResponsePacket,
+    },
+    read::{MinecraftReadable, MinecraftReadableVar},
+    stream::Cursor,
+};
+
+async fn read<T: MinecraftReadable<Cursor>>(data: &[u8]) {
+    let _ = T::read_from(&mut Cursor::new(data.to_vec())).await;
+}
+
+async fn read_var<T: MinecraftReadableVar<Cursor>>(data: &[u8]) {
+    let _ = T::read_var_from(&mut Cursor::new(data.to_vec())).await;
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&which, data)) = data.split_first() else {
+        return;
+    };
+
+    block_on(async {
+        match which % 31 {
+            0 => read::<bool>(data).await,
+            1 => read::<i8>(data).await,
+            2 => read::<u8>(data).await,
+            3 => read::<i16>(data).await,
+            4 => read::<u16>(data).await,
+            5 => read::<i32>(data).await,
+            6 => read::<u32>(data).await,
+            7 => read::<i64>(data).await,
+            8 => read::<u64>(data).await,
+            9 => read::<i128>(data).await,
+            10 => read::<u128>(data).await,
+            11 => read::<f32>(data).await,
+            12 => read::<f64>(data).await,
+            13 => read::<String>(data).await,
+            14 => read::<Option<String>>(data).await,
+            15 => read::<Vec<String>>(data).await,
+            16 => read::<[u16; 4]>(data).await,
+            17 => read_var::<i32>(data).await,
+            18 => read_var::<i64>(data).await,
+            19 => read_var::<Option<i32>>(data).await,
+            20 => read_var::<Vec<i64>>(data).await,
+            21 => read::<NextState>(data).await,
+            22 => read::<HandshakePacket>(data).await,
+            23 => read::<StatusRequestPacket>(data).await,
+            24 => read::<StatusResponsePacket>(data).await,
+            25 => read::<PingRequestPacket>(data).await,
+            26 => read::<PongResponsePacket>(data).await,
+            27 => read::<LoginStartPacket>(data).await,
+            28 => read::<LoginDisconnectPacket>(data).await,
+            29 => read::<SetCompressionPacket>(data).await,
+            _ => {
+                let _ = read_legacy_ping(&mut Cursor::new(data.to_vec())).await;
+            }
+        }
+    });
+});
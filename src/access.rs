@@ -0,0 +1,176 @@
+use std::{collections::HashSet, fs, net::IpAddr, path::Path};
+
+use crate::{config::ConfigError, maintenance::parse_uuid};
+
+/// Usernames loaded from a file.
+///
+/// Each line is a username. Empty lines and anything after a `#` are ignored. UUIDs are rejected,
+/// since clients send whichever UUID they like before logging in.
+#[derive(Debug, Clone, Default)]
+pub struct PlayerList {
+    /// Lowercase, since usernames aren't case sensitive.
+    names: HashSet<String>,
+}
+
+impl PlayerList {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let mut list = Self::default();
+        for (line, entry) in read_list(path)? {
+            if parse_uuid(&entry).is_some() {
+                return Err(ConfigError::UuidListEntry {
+                    path: path.to_owned(),
+                    line,
+                    entry,
+                });
+            } else if is_username(&entry) {
+                list.names.insert(entry.to_ascii_lowercase());
+            } else {
+                return Err(ConfigError::InvalidListEntry {
+                    path: path.to_owned(),
+                    line,
+                    entry,
+                });
+            }
+        }
+        Ok(list)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(&name.to_ascii_lowercase())
+    }
+}
+
+fn is_username(name: &str) -> bool {
+    (1..=16).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Reads the entries of a list file along with their line numbers.
+fn read_list(path: &Path) -> Result<Vec<(usize, String)>, ConfigError> {
+    let contents = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    Ok(contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let entry = line.split('#').next().unwrap_or_default().trim();
+            (index + 1, entry.to_owned())
+        })
+        .filter(|(_, entry)| !entry.is_empty())
+        .collect())
+}
+
+/// An IPv4 or IPv6 network, like `10.0.0.0/8` or `2001:db8::/32`. A plain ip is a network with
+/// just that ip in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    ip: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn parse(network: &str) -> Option<Self> {
+        let (ip, prefix) = match network.split_once('/') {
+            Some((ip, prefix)) => (ip.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+            None => (network.parse::<IpAddr>().ok()?, None),
+        };
+        let bits = if ip.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(bits);
+        (prefix <= bits).then_some(Self { ip, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // dual stack sockets give IPv4 clients as ::ffff:a.b.c.d
+        match (self.ip, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => mask(
+                u32::from(network).into(),
+                u32::from(ip).into(),
+                self.prefix,
+                32,
+            ),
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                mask(network.into(), ip.into(), self.prefix, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether the first `prefix` bits of two `bits` long addresses match.
+fn mask(network: u128, ip: u128, prefix: u8, bits: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix;
+    network >> shift == ip >> shift
+}
+
+/// Reads a file with one ip or network per line.
+pub fn load_networks(path: &Path) -> Result<Vec<IpNetwork>, ConfigError> {
+    read_list(path)?
+        .into_iter()
+        .map(|(line, entry)| {
+            IpNetwork::parse(&entry).ok_or_else(|| ConfigError::InvalidListEntry {
+                path: path.to_owned(),
+                line,
+                entry,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn ipv4_networks() {
+        let network = IpNetwork::parse("10.1.0.0/16").unwrap();
+        assert!(network.contains(ip("10.1.255.3")));
+        assert!(!network.contains(ip("10.2.0.1")));
+        assert!(network.contains(ip("::ffff:10.1.0.1")));
+        assert!(!network.contains(ip("::1")));
+
+        assert!(IpNetwork::parse("0.0.0.0/0")
+            .unwrap()
+            .contains(ip("8.8.8.8")));
+        assert!(IpNetwork::parse("1.2.3.4").unwrap().contains(ip("1.2.3.4")));
+        assert!(!IpNetwork::parse("1.2.3.4").unwrap().contains(ip("1.2.3.5")));
+    }
+
+    #[test]
+    fn ipv6_networks() {
+        let network = IpNetwork::parse("2001:db8::/32").unwrap();
+        assert!(network.contains(ip("2001:db8:ffff::1")));
+        assert!(!network.contains(ip("2001:db9::1")));
+        assert!(!network.contains(ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn player_lists() {
+        let path = std::env::temp_dir().join(format!("crafti-players-{}.txt", std::process::id()));
+        fs::write(&path, "Notch\n\n  jeb_ # a comment\n").unwrap();
+        let list = PlayerList::load(&path).unwrap();
+        assert!(list.contains("notch"));
+        assert!(list.contains("JEB_"));
+        assert!(!list.contains("Steve"));
+
+        fs::write(&path, "Notch\n069a79f4-44e9-4726-a5be-fca90e38aaf5\n").unwrap();
+        let err = PlayerList::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(err, ConfigError::UuidListEntry { line: 2, .. }));
+    }
+
+    #[test]
+    fn invalid_networks() {
+        assert_eq!(IpNetwork::parse("10.0.0.0/33"), None);
+        assert_eq!(IpNetwork::parse("::/129"), None);
+        assert_eq!(IpNetwork::parse("10.0.0/8"), None);
+        assert_eq!(IpNetwork::parse("example.net"), None);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    fmt::{self, Display},
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::Error;
+use async_std::{future::timeout, net::TcpStream};
+use thiserror::Error;
+
+use crate::{
+    protocol::{
+        packets::{HandshakePacket, NextState, StatusRequestPacket, StatusResponsePacket},
+        stream::{Framing, MinecraftStream},
+    },
+    status::StatusResponse,
+};
+
+#[derive(Error, Debug)]
+pub enum AddressError {
+    #[error("the address is empty")]
+    Empty,
+    #[error("`{0}` isn't a valid port")]
+    InvalidPort(String),
+    #[error("`{0}` isn't a valid hostname or ip")]
+    InvalidHost(String),
+}
+
+/// The address of a backend server.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BackendAddress {
+    /// An ip or hostname. IPv6 addresses are stored without brackets.
+    pub host: String,
+    pub port: u16,
+}
+
+impl BackendAddress {
+    /// Parses a backend address, using `default_port` if it doesn't include a port.
+    ///
+    /// Accepts ips, hostnames and IPv6 addresses with or without a port, e.g. `10.0.0.5:25566`,
+    /// `play.example.net`, `::1` or `[::1]:25570`.
+    pub fn parse(address: &str, default_port: u16) -> Result<Self, AddressError> {
+        let address = address.trim();
+        if address.is_empty() {
+            return Err(AddressError::Empty);
+        }
+
+        if let Ok(socket) = address.parse::<SocketAddr>() {
+            return Ok(Self {
+                host: socket.ip().to_string(),
+                port: parse_port(&socket.port().to_string())?,
+            });
+        }
+        if let Ok(ip) = address.parse::<IpAddr>() {
+            return Ok(Self {
+                host: ip.to_string(),
+                port: default_port,
+            });
+        }
+
+        if let Some(rest) = address.strip_prefix('[') {
+            let (ip, port) = rest
+                .split_once(']')
+                .ok_or_else(|| AddressError::InvalidHost(address.to_owned()))?;
+            let ip = ip
+                .parse::<IpAddr>()
+                .map_err(|_| AddressError::InvalidHost(ip.to_owned()))?;
+            let port = match port {
+                "" => default_port,
+                port => parse_port(
+                    port.strip_prefix(':')
+                        .ok_or_else(|| AddressError::InvalidHost(address.to_owned()))?,
+                )?,
+            };
+            return Ok(Self {
+                host: ip.to_string(),
+                port,
+            });
+        }
+
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host, parse_port(port)?),
+            None => (address, default_port),
+        };
+        let valid = !host.is_empty()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_');
+        if !valid {
+            return Err(AddressError::InvalidHost(host.to_owned()));
+        }
+
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+        })
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, AddressError> {
+    match port.parse::<u16>() {
+        Ok(port) if port != 0 => Ok(port),
+        _ => Err(AddressError::InvalidPort(port.to_owned())),
+    }
+}
+
+impl Display for BackendAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Asks a backend for its status, the same way a client in the server list would.
+///
+/// `handshake` is sent as is, apart from `next_state` which is always set to status.
+pub async fn fetch_status(
+    target: &BackendAddress,
+    mut handshake: HandshakePacket,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    framing: Framing,
+) -> Result<StatusResponse, Error> {
+    let mut server = timeout(
+        connect_timeout,
+        TcpStream::connect((target.host.as_str(), target.port)),
+    )
+    .await??;
+
+    handshake.next_state = NextState::Status;
+    let status: StatusResponsePacket = timeout(read_timeout, async {
+        server.write_packet(&mut handshake).await?;
+        server.write_packet(&mut StatusRequestPacket {}).await?;
+        server.read_packet_framed(&framing).await
+    })
+    .await??;
+    Ok(StatusResponse::parse(&status.response)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_addresses() {
+        for (address, host, port) in [
+            ("[::1]:25570", "::1", 25570),
+            ("[::1]", "::1", 25565),
+            ("::1", "::1", 25565),
+            ("10.0.0.5:25566", "10.0.0.5", 25566),
+            ("10.0.0.5", "10.0.0.5", 25565),
+            ("play.example.net:25570", "play.example.net", 25570),
+            ("play.example.net", "play.example.net", 25565),
+            ("  localhost ", "localhost", 25565),
+        ] {
+            let parsed = BackendAddress::parse(address, 25565).unwrap();
+            assert_eq!(
+                (parsed.host.as_str(), parsed.port),
+                (host, port),
+                "{}",
+                address
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        for address in [
+            "",
+            "  ",
+            "[::1]25570",
+            "[::1]:",
+            "[::1",
+            "[example.net]:25565",
+            "10.0.0.5:0",
+            "10.0.0.5:65536",
+            "play.example.net:port",
+            ":25565",
+            "play example.net",
+            "play/example.net",
+        ] {
+            assert!(
+                BackendAddress::parse(address, 25565).is_err(),
+                "{} should be invalid",
+                address
+            );
+        }
+    }
+
+    #[test]
+    fn displays_addresses() {
+        let parse = |address| BackendAddress::parse(address, 25565).unwrap().to_string();
+        assert_eq!(parse("::1"), "[::1]:25565");
+        assert_eq!(parse("play.example.net:25570"), "play.example.net:25570");
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use async_std::task::spawn;
+use colored::Colorize;
+
+use crate::{backend::BackendAddress, status::StatusResponse};
+
+/// How many responses are cached for each backend. Once a backend has this many, every other
+/// host and protocol version for it shares one more.
+const MAX_SLOTS_PER_BACKEND: usize = 32;
+
+/// Backends are cached separately for each host and protocol version, since they can answer
+/// differently, e.g. behind forced hosts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub backend: BackendAddress,
+    /// The address sent to the backend in the handshake.
+    pub host: String,
+    /// Clients can send any number here, so only versions the backend has answered with, or
+    /// that it answered differently from the shared response, get their own response. `None` is
+    /// shared by all the others.
+    pub protocol_version: Option<i32>,
+}
+
+#[derive(Debug)]
+struct Cached {
+    status: StatusResponse,
+    fetched: Instant,
+    refreshing: bool,
+}
+
+type Slot = Arc<async_std::sync::Mutex<Option<Cached>>>;
+
+/// Caches backend status responses so pings don't all reach the backend.
+///
+/// Responses younger than `ttl` are used as is. Responses younger than `ttl + stale` are still
+/// used, but get refreshed in the background. Anything older is fetched again, and other pings for
+/// the same backend wait for that fetch instead of starting their own.
+#[derive(Debug, Default)]
+pub struct StatusCache {
+    slots: Mutex<HashMap<CacheKey, Slot>>,
+    /// The protocol versions each backend has answered with.
+    known: Mutex<HashMap<BackendAddress, HashSet<i32>>>,
+}
+
+impl StatusCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn get<F, Fut>(
+        self: &Arc<Self>,
+        key: CacheKey,
+        ttl: Duration,
+        stale: Duration,
+        fetch: F,
+    ) -> Result<StatusResponse, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<StatusResponse, Error>> + Send + 'static,
+    {
+        if ttl.is_zero() {
+            return fetch().await;
+        }
+
+        let requested = key.clone();
+        let (mut key, mut slot) = self.slot(key, ttl + stale);
+        if key != requested {
+            // backends like ViaVersion answer each version differently, so a shared response for
+            // another version would show the wrong version, or mark the client as outdated
+            let answered = slot
+                .lock()
+                .await
+                .as_ref()
+                .map(|entry| entry.status.version.protocol);
+            if answered.is_some_and(|answered| requested.protocol_version != Some(answered)) {
+                if let Some(own) = self.own_slot(&requested) {
+                    (key, slot) = (requested, own);
+                }
+            }
+        }
+        let mut cached = slot.lock().await;
+
+        if let Some(entry) = cached.as_mut() {
+            let age = entry.fetched.elapsed();
+            if age < ttl {
+                return Ok(entry.status.clone());
+            }
+            if age < ttl + stale {
+                if !entry.refreshing {
+                    entry.refreshing = true;
+                    spawn(self.clone().refresh(slot.clone(), key, fetch()));
+                }
+                return Ok(entry.status.clone());
+            }
+        }
+
+        // keep the slot locked so other pings wait for this instead of fetching too
+        let status = fetch().await;
+        if let Ok(status) = &status {
+            self.learn(&key.backend, status.version.protocol);
+        }
+        *cached = status.as_ref().ok().map(|status| Cached {
+            status: status.clone(),
+            fetched: Instant::now(),
+            refreshing: false,
+        });
+        status
+    }
+
+    /// Gets the slot for a backend, dropping any that have expired while we're here.
+    ///
+    /// The key is moved to a shared slot if its protocol version isn't known and has no slot of
+    /// its own, or if the backend already has too many slots, so made up handshakes can't make a
+    /// fetch each.
+    fn slot(&self, mut key: CacheKey, max_age: Duration) -> (CacheKey, Slot) {
+        let known = key.protocol_version.is_some_and(|protocol_version| {
+            self.known
+                .lock()
+                .unwrap()
+                .get(&key.backend)
+                .is_some_and(|versions| versions.contains(&protocol_version))
+        });
+
+        let mut slots = self.slots.lock().unwrap();
+        slots.retain(|_, slot| {
+            // slots are only cloned while `slots` is locked, so if nothing else holds this one,
+            // nobody is using it
+            Arc::strong_count(slot) > 1
+                || slot.try_lock().is_some_and(|cached| {
+                    cached
+                        .as_ref()
+                        .is_some_and(|entry| entry.fetched.elapsed() < max_age)
+                })
+        });
+        if !known && !slots.contains_key(&key) {
+            key.protocol_version = None;
+        }
+        if !slots.contains_key(&key) && is_full(&slots, &key.backend) {
+            key = CacheKey {
+                backend: key.backend,
+                host: String::new(),
+                protocol_version: None,
+            };
+        }
+        let slot = slots.entry(key.clone()).or_default().clone();
+        (key, slot)
+    }
+
+    /// Gives a key a slot of its own, unless its backend already has too many.
+    fn own_slot(&self, key: &CacheKey) -> Option<Slot> {
+        let mut slots = self.slots.lock().unwrap();
+        if !slots.contains_key(key) && is_full(&slots, &key.backend) {
+            return None;
+        }
+        Some(slots.entry(key.clone()).or_default().clone())
+    }
+
+    fn learn(&self, backend: &BackendAddress, protocol_version: i32) {
+        let mut known = self.known.lock().unwrap();
+        let versions = known.entry(backend.clone()).or_default();
+        if versions.len() < MAX_SLOTS_PER_BACKEND {
+            versions.insert(protocol_version);
+        }
+    }
+
+    async fn refresh<Fut>(self: Arc<Self>, slot: Slot, key: CacheKey, fetch: Fut)
+    where
+        Fut: Future<Output = Result<StatusResponse, Error>>,
+    {
+        let status = fetch.await;
+        let mut cached = slot.lock().await;
+        match status {
+            Ok(status) => {
+                self.learn(&key.backend, status.version.protocol);
+                *cached = Some(Cached {
+                    status,
+                    fetched: Instant::now(),
+                    refreshing: false,
+                })
+            }
+            Err(err) => {
+                println!(
+                    "{} {}{} {}",
+                    "Couldn't refresh the status of".yellow(),
+                    key.backend.to_string().bold(),
+                    ":".yellow(),
+                    err
+                );
+                if let Some(entry) = cached.as_mut() {
+                    entry.refreshing = false;
+                }
+            }
+        }
+    }
+}
+
+fn is_full(slots: &HashMap<CacheKey, Slot>, backend: &BackendAddress) -> bool {
+    slots.keys().filter(|key| &key.backend == backend).count() >= MAX_SLOTS_PER_BACKEND
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use async_std::task::block_on;
+
+    use super::*;
+    use crate::status::StatusResponse;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn key(host: &str, protocol_version: i32) -> CacheKey {
+        CacheKey {
+            backend: BackendAddress {
+                host: "10.0.0.2".to_owned(),
+                port: 25565,
+            },
+            host: host.to_owned(),
+            protocol_version: Some(protocol_version),
+        }
+    }
+
+    /// Gets a status, answering with `protocol` and counting how often the backend was asked.
+    /// Returns the protocol version in the response that was used.
+    fn get(
+        cache: &Arc<StatusCache>,
+        key: CacheKey,
+        protocol: i32,
+        fetches: &Arc<AtomicU32>,
+    ) -> i32 {
+        let fetches = fetches.clone();
+        block_on(cache.get(key, TTL, Duration::ZERO, || async move {
+            fetches.fetch_add(1, Ordering::Relaxed);
+            Ok(StatusResponse::parse(&format!(
+                r#"{{"version":{{"name":"1.21","protocol":{}}}}}"#,
+                protocol
+            ))?)
+        }))
+        .unwrap()
+        .version
+        .protocol
+    }
+
+    #[test]
+    fn hosts_are_cached_separately() {
+        let cache = Arc::new(StatusCache::new());
+        let fetches = Arc::new(AtomicU32::new(0));
+        // learn that the backend answers with 767
+        get(&cache, key("c.example", 767), 767, &fetches);
+        fetches.store(0, Ordering::Relaxed);
+
+        get(&cache, key("a.example", 767), 767, &fetches);
+        get(&cache, key("b.example", 767), 767, &fetches);
+        get(&cache, key("a.example", 767), 767, &fetches);
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn unknown_protocol_versions_share_a_response_for_the_same_version() {
+        let cache = Arc::new(StatusCache::new());
+        let fetches = Arc::new(AtomicU32::new(0));
+        // the backend answers with the client's version, like ViaVersion does
+        assert_eq!(get(&cache, key("a.example", 47), 47, &fetches), 47);
+        assert_eq!(get(&cache, key("a.example", 767), 767, &fetches), 767);
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+
+        // the backend answered with 767, so it has a response of its own now
+        assert_eq!(get(&cache, key("a.example", 767), 767, &fetches), 767);
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn unknown_protocol_versions_have_a_limited_number_of_responses() {
+        let cache = Arc::new(StatusCache::new());
+        let fetches = Arc::new(AtomicU32::new(0));
+        for round in 0..2 {
+            // the backend answers every version with its own
+            for protocol_version in 0..100 {
+                get(&cache, key("a.example", protocol_version), 767, &fetches);
+            }
+            assert_eq!(
+                fetches.load(Ordering::Relaxed),
+                MAX_SLOTS_PER_BACKEND as u32,
+                "round {}",
+                round
+            );
+        }
+    }
+
+    #[test]
+    fn backends_have_a_limited_number_of_responses() {
+        let cache = Arc::new(StatusCache::new());
+        let fetches = Arc::new(AtomicU32::new(0));
+        for round in 0..2 {
+            for host in 0..100 {
+                get(
+                    &cache,
+                    key(&format!("{}.example", host), 767),
+                    767,
+                    &fetches,
+                );
+            }
+            assert_eq!(
+                fetches.load(Ordering::Relaxed),
+                MAX_SLOTS_PER_BACKEND as u32 + 1,
+                "round {}",
+                round
+            );
+        }
+    }
+}
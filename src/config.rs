@@ -0,0 +1,989 @@
+use std::{
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    access::{load_networks, IpNetwork, PlayerList},
+    backend::{AddressError, BackendAddress},
+    maintenance::parse_uuid,
+    motd::{parse_time, MotdMode, MotdPool},
+    protocol::{
+        read::{CollectionLimits, DEFAULT_MAX_STRING_LENGTH, DEFAULT_MAX_VEC_LENGTH},
+        stream::{Framing, MAX_PACKET_LENGTH},
+    },
+    route::RouteConfig,
+    status::text_component,
+    template::{unknown_placeholder, PLACEHOLDERS},
+};
+
+/// The config file that is loaded if `--config` isn't passed.
+pub const DEFAULT_CONFIG_PATH: &str = "crafti.toml";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Couldn't read config file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Couldn't parse config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("No target ip set; pass one as an argument or set `target_ip` in the config")]
+    MissingTarget,
+    #[error("Invalid `{field}` `{address}`: {source}")]
+    InvalidTarget {
+        field: String,
+        address: String,
+        source: AddressError,
+    },
+    #[error("Invalid listening address `{address}`; expected ip:port, e.g. 0.0.0.0:25565")]
+    InvalidListeningAddress { address: String },
+    #[error("Invalid `{field}`; it must be a JSON text component: {source}")]
+    InvalidMotd {
+        field: String,
+        source: serde_json::Error,
+    },
+    #[error("Route {index} has no hosts")]
+    MissingRouteHosts { index: usize },
+    #[error("Route {index} has no `target_ip`")]
+    MissingRouteTarget { index: usize },
+    #[error("Invalid host `{host}`; wildcards are only allowed at the start, e.g. *.example.net")]
+    InvalidHost { host: String },
+    #[error("Invalid timeout `{field}`; it must be greater than 0")]
+    InvalidTimeout { field: &'static str },
+    #[error("Invalid `{field}`; it must be at least 1")]
+    InvalidThreshold { field: &'static str },
+    #[error("Unknown placeholder `{{{placeholder}}}` in `{field}`; expected one of {}", PLACEHOLDERS.join(", "))]
+    UnknownPlaceholder { field: String, placeholder: String },
+    #[error("Invalid entry `{entry}` on line {line} of {path}")]
+    InvalidListEntry {
+        path: PathBuf,
+        line: usize,
+        entry: String,
+    },
+    #[error("Invalid ip or network `{network}` in `{field}`; expected e.g. 10.0.0.0/8 or ::1")]
+    InvalidNetwork { field: String, network: String },
+    #[error("Invalid `{field}`; per_second can't be negative, and burst must be at least 1")]
+    InvalidRateLimit { field: &'static str },
+    #[error("Invalid packet limit `{field}`; it must be greater than 0")]
+    InvalidPacketLimit { field: &'static str },
+    #[error("`{field}` has no entries")]
+    EmptyMotdPool { field: String },
+    #[error("`{field}` has no entries with a weight above 0")]
+    MissingMotdWeight { field: String },
+    #[error("Invalid `{field}` `{time}`; expected HH:MM, e.g. 18:30")]
+    InvalidTime { field: String, time: String },
+    #[error("Can't use UUID `{entry}` in `{field}`, since clients pick the UUID they log in with; use their username instead")]
+    UuidEntry { field: &'static str, entry: String },
+    #[error("Can't use UUID `{entry}` on line {line} of {path}, since clients pick the UUID they log in with; use their username instead")]
+    UuidListEntry {
+        path: PathBuf,
+        line: usize,
+        entry: String,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The address of the backend server, e.g. `10.0.0.5`, `10.0.0.5:25566` or `[::1]:25570`.
+    pub target_ip: String,
+    /// The port of the backend server, if `target_ip` doesn't have one.
+    pub target_port: u16,
+    /// The parsed backend address. This is filled in by [`Config::validate`].
+    #[serde(skip)]
+    pub target: BackendAddress,
+    /// The address the proxy listens on, e.g. 0.0.0.0:25565
+    pub listening_ip: String,
+    /// The MOTD shown while the backend is online, as a JSON text component.
+    pub motd: String,
+    /// The MOTD shown while the backend is offline.
+    ///
+    /// If this isn't set, it is built from [`Config::motd`] with "(Offline)" added to the end.
+    pub offline_motd: Option<String>,
+    /// A list of MOTDs to use instead of `motd`.
+    pub motds: Option<MotdPool>,
+    /// A list of MOTDs to use instead of `offline_motd`.
+    pub offline_motds: Option<MotdPool>,
+    /// The MOTDs that are actually shown. This is filled in by [`Config::validate`].
+    #[serde(skip)]
+    pub motd_pool: MotdPool,
+    /// The offline MOTDs that are actually shown. This is filled in by [`Config::validate`].
+    #[serde(skip)]
+    pub offline_motd_pool: MotdPool,
+    /// The favicon as a base64 PNG data URI. Empty if there is none.
+    pub favicon: String,
+    /// The hostname sent to the backend in the handshake.
+    ///
+    /// If this isn't set, status requests forward the address the client connected with and
+    /// logins send the backend's own address.
+    pub rewrite_host: Option<String>,
+    /// How often the config file is checked for changes, in milliseconds. 0 turns reloading off.
+    pub reload_interval: u64,
+    pub timeouts: Timeouts,
+    pub status_cache: StatusCacheConfig,
+    pub health_check: HealthCheckConfig,
+    pub version: VersionConfig,
+    pub players: PlayersConfig,
+    pub sample: SampleConfig,
+    pub messages: Messages,
+    pub maintenance: MaintenanceConfig,
+    pub player_lists: PlayerListsConfig,
+    pub ip_lists: IpListsConfig,
+    pub rate_limits: RateLimitsConfig,
+    pub packet_limits: PacketLimitsConfig,
+    /// Per hostname backends. Hosts that don't match any route use the settings above.
+    pub routes: Vec<RouteConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            target_ip: "".to_owned(),
+            target_port: 25565,
+            target: BackendAddress::default(),
+            listening_ip: "0.0.0.0:25565".to_string(),
+            motd: r#"[{"text":"A ","color":"gold"},{"text":"nano-mc","color":"green"},{"text":" proxy.","color":"gold"}]"#
+                .to_string(),
+            offline_motd: None,
+            motds: None,
+            offline_motds: None,
+            motd_pool: MotdPool::default(),
+            offline_motd_pool: MotdPool::default(),
+            favicon: "".to_string(),
+            rewrite_host: None,
+            reload_interval: 2000,
+            timeouts: Timeouts::default(),
+            status_cache: StatusCacheConfig::default(),
+            health_check: HealthCheckConfig::default(),
+            version: VersionConfig::default(),
+            players: PlayersConfig::default(),
+            sample: SampleConfig::default(),
+            messages: Messages::default(),
+            maintenance: MaintenanceConfig::default(),
+            player_lists: PlayerListsConfig::default(),
+            ip_lists: IpListsConfig::default(),
+            rate_limits: RateLimitsConfig::default(),
+            packet_limits: PacketLimitsConfig::default(),
+            routes: vec![],
+        }
+    }
+}
+
+/// All timeouts are in milliseconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    /// How long to wait when connecting to the backend before treating it as offline.
+    pub backend_connect: u64,
+    /// How long the backend has to answer a status request once connected.
+    pub backend_read: u64,
+    /// How long clients have to send their handshake after connecting.
+    pub handshake: u64,
+    /// How long clients have to send each status request and ping.
+    pub status: u64,
+    /// How long clients have to send their login start after the handshake.
+    pub login_start: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            backend_connect: 1500,
+            backend_read: 3000,
+            handshake: 5000,
+            status: 5000,
+            login_start: 5000,
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn backend_connect(&self) -> Duration {
+        Duration::from_millis(self.backend_connect)
+    }
+
+    pub fn backend_read(&self) -> Duration {
+        Duration::from_millis(self.backend_read)
+    }
+
+    pub fn handshake(&self) -> Duration {
+        Duration::from_millis(self.handshake)
+    }
+
+    pub fn status(&self) -> Duration {
+        Duration::from_millis(self.status)
+    }
+
+    pub fn login_start(&self) -> Duration {
+        Duration::from_millis(self.login_start)
+    }
+}
+
+/// How long backend status responses are reused for, in milliseconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusCacheConfig {
+    /// How long a response is used before it is refreshed. 0 turns the cache off.
+    pub ttl: u64,
+    /// How long an old response can still be used while it is refreshed in the background.
+    pub stale: u64,
+}
+
+impl Default for StatusCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: 5000,
+            stale: 30000,
+        }
+    }
+}
+
+impl StatusCacheConfig {
+    pub fn ttl(&self) -> Duration {
+        Duration::from_millis(self.ttl)
+    }
+
+    pub fn stale(&self) -> Duration {
+        Duration::from_millis(self.stale)
+    }
+}
+
+/// How backends are checked in the background.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthCheckConfig {
+    /// How often each backend is pinged, in milliseconds. 0 turns health checks off.
+    pub interval: u64,
+    /// How many pings in a row have to work before a backend is marked as up.
+    pub rise: u32,
+    /// How many pings in a row have to fail before a backend is marked as down.
+    pub fall: u32,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            interval: 5000,
+            rise: 2,
+            fall: 2,
+        }
+    }
+}
+
+/// What version is shown in the server list.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VersionConfig {
+    /// Replaces the version name sent by the backend. The protocol number is always the backend's.
+    pub name: Option<String>,
+    /// The version name shown while the backend is offline.
+    pub offline_name: String,
+    /// The protocol number shown while the backend is offline.
+    ///
+    /// If this isn't set, the client's own protocol number is sent back.
+    pub offline_protocol: Option<i32>,
+}
+
+impl Default for VersionConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            offline_name: "Offline".to_owned(),
+            offline_protocol: None,
+        }
+    }
+}
+
+/// Where the max player count in the server list comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaxPlayers {
+    /// The backend's own max players.
+    #[default]
+    Backend,
+    /// Always [`PlayersConfig::max`].
+    Fixed,
+    /// One more than the online count, so the server never looks full.
+    OnlinePlusOne,
+}
+
+/// Changes the player counts sent by the backend.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayersConfig {
+    pub max_mode: MaxPlayers,
+    /// The max players shown when `max_mode` is `fixed`.
+    pub max: i32,
+    /// Backends whose online counts are added up and shown instead of the backend's own, e.g. to
+    /// show everyone on the network.
+    pub online_from: Vec<String>,
+    /// The parsed `online_from` addresses. This is filled in by [`Config::validate`].
+    #[serde(skip)]
+    pub online_backends: Vec<BackendAddress>,
+}
+
+impl Default for PlayersConfig {
+    fn default() -> Self {
+        Self {
+            max_mode: MaxPlayers::Backend,
+            max: 100,
+            online_from: vec![],
+            online_backends: vec![],
+        }
+    }
+}
+
+/// Whether the configured sample entries are added to the backend's or used instead of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleMode {
+    #[default]
+    Append,
+    Replace,
+}
+
+/// The list of players shown when hovering over the player count in the server list.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SampleConfig {
+    pub mode: SampleMode,
+    /// Shows the backend's players as "Anonymous Player" instead of their names.
+    pub hide_names: bool,
+    /// Lines of text to show, which can use `§` colour codes and the same placeholders as MOTDs.
+    pub entries: Vec<String>,
+}
+
+/// Messages shown to players when they get disconnected.
+///
+/// These can be plain text or JSON text components, with the same placeholders as MOTDs.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Messages {
+    /// Shown when the backend can't be reached while logging in.
+    pub backend_offline: String,
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Self {
+            backend_offline: r#"[{"text":"The server is offline","color":"red"},{"text":", try again in a minute.","color":"gray"}]"#
+                .to_owned(),
+        }
+    }
+}
+
+/// What is shown while the proxy is in maintenance.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceConfig {
+    /// Whether the proxy starts in maintenance. This can also be switched from the console.
+    pub enabled: bool,
+    /// The MOTD shown instead of the normal one, as a JSON text component.
+    pub motd: String,
+    /// The version name shown in the server list.
+    pub version: String,
+    /// The reason shown to players who aren't allowed to join.
+    pub message: String,
+    /// Usernames of the players who can still join. UUIDs aren't allowed, since clients send
+    /// whichever UUID they like before logging in.
+    pub allowlist: Vec<String>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            motd: r#"[{"text":"Down for maintenance","color":"gold"},{"text":", back soon!","color":"gray"}]"#
+                .to_owned(),
+            version: "Maintenance".to_owned(),
+            message: r#"[{"text":"The server is down for maintenance","color":"gold"},{"text":", try again later.","color":"gray"}]"#
+                .to_owned(),
+            allowlist: vec![],
+        }
+    }
+}
+
+impl MaintenanceConfig {
+    /// Whether a player can join while the proxy is in maintenance.
+    pub fn allows(&self, name: &str) -> bool {
+        self.allowlist
+            .iter()
+            .any(|entry| entry.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Players who can or can't join, loaded from files that are reloaded when they change.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerListsConfig {
+    /// If this is set, only the players in this file can join.
+    pub allowlist_file: Option<PathBuf>,
+    /// The players in this file can't join.
+    pub denylist_file: Option<PathBuf>,
+    /// The reason shown to players who can't join.
+    pub message: String,
+    /// The players loaded from `allowlist_file`. This is filled in by [`Config::validate`].
+    #[serde(skip)]
+    pub allowlist: Option<PlayerList>,
+    /// The players loaded from `denylist_file`. This is filled in by [`Config::validate`].
+    #[serde(skip)]
+    pub denylist: PlayerList,
+}
+
+impl Default for PlayerListsConfig {
+    fn default() -> Self {
+        Self {
+            allowlist_file: None,
+            denylist_file: None,
+            message: r#"{"text":"You are not allowed to join this server.","color":"red"}"#
+                .to_owned(),
+            allowlist: None,
+            denylist: PlayerList::default(),
+        }
+    }
+}
+
+impl PlayerListsConfig {
+    pub fn allows(&self, name: &str) -> bool {
+        !self.denylist.contains(name)
+            && self
+                .allowlist
+                .as_ref()
+                .is_none_or(|allowlist| allowlist.contains(name))
+    }
+}
+
+/// Which ips can connect.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IpListsConfig {
+    /// Checked as soon as a connection is accepted, before anything is read.
+    pub all: IpRules,
+    /// Checked for server list pings, after the handshake.
+    pub status: IpRules,
+    /// Checked for logins, after the handshake.
+    pub login: IpRules,
+}
+
+/// Ips and networks that are allowed or denied, e.g. `10.0.0.0/8`, `1.2.3.4` or `2001:db8::/32`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IpRules {
+    /// If this or `allow_file` has anything in it, only these ips can connect.
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    /// A file with one ip or network per line, reloaded when it changes.
+    pub allow_file: Option<PathBuf>,
+    pub deny_file: Option<PathBuf>,
+    /// Everything in `allow` and `allow_file`. This is filled in by [`Config::validate`].
+    #[serde(skip)]
+    pub allowed: Vec<IpNetwork>,
+    /// Everything in `deny` and `deny_file`. This is filled in by [`Config::validate`].
+    #[serde(skip)]
+    pub denied: Vec<IpNetwork>,
+}
+
+impl IpRules {
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        !self.denied.iter().any(|network| network.contains(ip))
+            && (self.allowed.is_empty() || self.allowed.iter().any(|network| network.contains(ip)))
+    }
+
+    fn load(&mut self, field: &str) -> Result<(), ConfigError> {
+        self.allowed = parse_networks(&format!("{}.allow", field), &self.allow)?;
+        if let Some(path) = &self.allow_file {
+            self.allowed.extend(load_networks(path)?);
+        }
+        self.denied = parse_networks(&format!("{}.deny", field), &self.deny)?;
+        if let Some(path) = &self.deny_file {
+            self.denied.extend(load_networks(path)?);
+        }
+        Ok(())
+    }
+}
+
+fn parse_networks(field: &str, networks: &[String]) -> Result<Vec<IpNetwork>, ConfigError> {
+    networks
+        .iter()
+        .map(|network| {
+            IpNetwork::parse(network).ok_or_else(|| ConfigError::InvalidNetwork {
+                field: field.to_owned(),
+                network: network.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Limits on what each ip can do.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitsConfig {
+    /// Server list pings, including legacy ones.
+    pub status: RateLimit,
+    pub login: RateLimit,
+    /// How many connections each ip can have open at once. 0 turns the limit off.
+    pub max_connections: u32,
+    /// The reason shown to players who log in too often.
+    pub message: String,
+}
+
+impl Default for RateLimitsConfig {
+    fn default() -> Self {
+        Self {
+            status: RateLimit {
+                per_second: 5.0,
+                burst: 20,
+            },
+            login: RateLimit {
+                per_second: 0.5,
+                burst: 5,
+            },
+            max_connections: 16,
+            message: r#"{"text":"Too many connections, try again in a minute.","color":"red"}"#
+                .to_owned(),
+        }
+    }
+}
+
+/// How big packets can be, in bytes, and how long strings and lists in them can be.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PacketLimitsConfig {
+    /// The longest handshake clients can send.
+    pub handshake: u32,
+    /// The longest status request or ping clients can send.
+    pub status: u32,
+    /// The longest login start clients can send.
+    pub login: u32,
+    /// The longest status response backends can send.
+    pub backend_status: u32,
+    /// How long strings and lists clients can send. Backends aren't held to these, so a tight
+    /// limit doesn't cut off their favicons.
+    pub max_string_length: u32,
+    pub max_vec_length: u32,
+}
+
+impl Default for PacketLimitsConfig {
+    fn default() -> Self {
+        Self {
+            handshake: 2048,
+            status: 64,
+            login: 4096,
+            backend_status: MAX_PACKET_LENGTH,
+            max_string_length: DEFAULT_MAX_STRING_LENGTH,
+            max_vec_length: DEFAULT_MAX_VEC_LENGTH,
+        }
+    }
+}
+
+impl PacketLimitsConfig {
+    pub fn handshake(&self) -> Framing {
+        self.client(self.handshake)
+    }
+
+    pub fn status(&self) -> Framing {
+        self.client(self.status)
+    }
+
+    pub fn login(&self) -> Framing {
+        self.client(self.login)
+    }
+
+    pub fn backend_status(&self) -> Framing {
+        Framing::limited(self.backend_status)
+    }
+
+    fn client(&self, max_length: u32) -> Framing {
+        Framing {
+            limits: CollectionLimits {
+                max_string_length: self.max_string_length,
+                max_vec_length: self.max_vec_length,
+            },
+            ..Framing::limited(max_length)
+        }
+    }
+}
+
+/// A token bucket: `burst` can be used at once, and `per_second` more are added every second.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// 0 turns the limit off.
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+impl Config {
+    /// Reads and parses a config file. This doesn't validate it, see [`Config::validate`].
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+
+        toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    /// Loads, overrides and validates a config file in one go.
+    pub fn load_with_args(path: &Path, args: &[String]) -> Result<Self, ConfigError> {
+        let mut config = Self::load(path)?;
+        config.apply_args(args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Overrides the config with the old positional arguments:
+    /// `[target-ip] [listening-ip:port] [motd] [favicon]`
+    pub fn apply_args(&mut self, args: &[String]) {
+        if let Some(target_ip) = args.first() {
+            self.target_ip = target_ip.to_owned();
+        }
+        if let Some(listening_ip) = args.get(1) {
+            self.listening_ip = listening_ip.to_owned();
+        }
+        if let Some(motd) = args.get(2) {
+            self.motd = motd.to_owned();
+            // the offline motds from the config file won't match the new motd anymore
+            self.offline_motd = None;
+            self.motds = None;
+            self.offline_motds = None;
+        }
+        if let Some(favicon) = args.get(3) {
+            self.favicon = favicon.to_owned();
+        }
+    }
+
+    /// Checks the config for mistakes and parses the backend addresses.
+    pub fn validate(&mut self) -> Result<(), ConfigError> {
+        if self.target_ip.is_empty() {
+            return Err(ConfigError::MissingTarget);
+        }
+        self.target = parse_target("target_ip", &self.target_ip, self.target_port)?;
+        if self.listening_ip.parse::<SocketAddr>().is_err() {
+            return Err(ConfigError::InvalidListeningAddress {
+                address: self.listening_ip.clone(),
+            });
+        }
+        validate_motd("motd", &self.motd)?;
+        self.motd_pool = match &self.motds {
+            Some(motds) => motds.clone(),
+            None => MotdPool::single(&self.motd),
+        };
+        self.offline_motd_pool = match (&self.offline_motds, &self.offline_motd) {
+            (Some(offline_motds), _) => offline_motds.clone(),
+            (None, Some(offline_motd)) => MotdPool::single(offline_motd),
+            (None, None) => self.motd_pool.offline(),
+        };
+        validate_pool("motds", &self.motd_pool)?;
+        validate_pool("offline_motds", &self.offline_motd_pool)?;
+        self.players.online_backends = self
+            .players
+            .online_from
+            .iter()
+            .enumerate()
+            .map(|(index, address)| {
+                parse_target(&format!("players.online_from[{}]", index), address, 25565)
+            })
+            .collect::<Result<_, _>>()?;
+        for (index, entry) in self.sample.entries.iter().enumerate() {
+            validate_placeholders(
+                &format!("sample.entries[{}]", index),
+                &serde_json::Value::String(entry.clone()),
+            )?;
+        }
+        validate_placeholders(
+            "messages.backend_offline",
+            &text_component(&self.messages.backend_offline),
+        )?;
+        validate_motd("maintenance.motd", &self.maintenance.motd)?;
+        if let Some(entry) = self
+            .maintenance
+            .allowlist
+            .iter()
+            .find(|entry| parse_uuid(entry).is_some())
+        {
+            return Err(ConfigError::UuidEntry {
+                field: "maintenance.allowlist",
+                entry: entry.clone(),
+            });
+        }
+        validate_placeholders(
+            "player_lists.message",
+            &text_component(&self.player_lists.message),
+        )?;
+        for (field, limit) in [
+            ("rate_limits.status", &self.rate_limits.status),
+            ("rate_limits.login", &self.rate_limits.login),
+        ] {
+            if limit.per_second.is_nan()
+                || limit.per_second < 0.0
+                || (limit.per_second > 0.0 && limit.burst == 0)
+            {
+                return Err(ConfigError::InvalidRateLimit { field });
+            }
+        }
+        validate_placeholders(
+            "rate_limits.message",
+            &text_component(&self.rate_limits.message),
+        )?;
+        self.ip_lists.all.load("ip_lists.all")?;
+        self.ip_lists.status.load("ip_lists.status")?;
+        self.ip_lists.login.load("ip_lists.login")?;
+        self.player_lists.allowlist = match &self.player_lists.allowlist_file {
+            Some(path) => Some(PlayerList::load(path)?),
+            None => None,
+        };
+        self.player_lists.denylist = match &self.player_lists.denylist_file {
+            Some(path) => PlayerList::load(path)?,
+            None => PlayerList::default(),
+        };
+        validate_placeholders(
+            "maintenance.message",
+            &text_component(&self.maintenance.message),
+        )?;
+        for (field, timeout) in [
+            ("timeouts.backend_connect", self.timeouts.backend_connect),
+            ("timeouts.backend_read", self.timeouts.backend_read),
+            ("timeouts.handshake", self.timeouts.handshake),
+            ("timeouts.status", self.timeouts.status),
+            ("timeouts.login_start", self.timeouts.login_start),
+        ] {
+            if timeout == 0 {
+                return Err(ConfigError::InvalidTimeout { field });
+            }
+        }
+
+        for (field, limit) in [
+            ("packet_limits.handshake", self.packet_limits.handshake),
+            ("packet_limits.status", self.packet_limits.status),
+            ("packet_limits.login", self.packet_limits.login),
+            (
+                "packet_limits.backend_status",
+                self.packet_limits.backend_status,
+            ),
+            (
+                "packet_limits.max_string_length",
+                self.packet_limits.max_string_length,
+            ),
+            (
+                "packet_limits.max_vec_length",
+                self.packet_limits.max_vec_length,
+            ),
+        ] {
+            if limit == 0 {
+                return Err(ConfigError::InvalidPacketLimit { field });
+            }
+        }
+
+        if self.health_check.rise == 0 {
+            return Err(ConfigError::InvalidThreshold {
+                field: "health_check.rise",
+            });
+        }
+        if self.health_check.fall == 0 {
+            return Err(ConfigError::InvalidThreshold {
+                field: "health_check.fall",
+            });
+        }
+
+        for (index, route) in self.routes.iter_mut().enumerate() {
+            if route.hosts.is_empty() {
+                return Err(ConfigError::MissingRouteHosts { index });
+            }
+            for host in &route.hosts {
+                let wildcard = host.strip_prefix("*.").unwrap_or(host);
+                if wildcard.is_empty() || wildcard.contains('*') {
+                    return Err(ConfigError::InvalidHost { host: host.clone() });
+                }
+            }
+            if route.target_ip.is_empty() {
+                return Err(ConfigError::MissingRouteTarget { index });
+            }
+            route.target = parse_target(
+                &format!("routes[{}].target_ip", index),
+                &route.target_ip,
+                route.target_port,
+            )?;
+            route.motd_pool = match (&route.motds, &route.motd) {
+                (Some(motds), _) => motds.clone(),
+                (None, Some(motd)) => MotdPool::single(motd),
+                (None, None) => self.motd_pool.clone(),
+            };
+            route.offline_motd_pool = match (&route.offline_motds, &route.offline_motd) {
+                (Some(offline_motds), _) => offline_motds.clone(),
+                (None, Some(offline_motd)) => MotdPool::single(offline_motd),
+                (None, None) if route.motds.is_some() || route.motd.is_some() => {
+                    route.motd_pool.offline()
+                }
+                (None, None) => self.offline_motd_pool.clone(),
+            };
+            validate_pool(&format!("routes[{}].motds", index), &route.motd_pool)?;
+            validate_pool(
+                &format!("routes[{}].offline_motds", index),
+                &route.offline_motd_pool,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Files other than the config file that the config is loaded from.
+    pub fn list_files(&self) -> Vec<PathBuf> {
+        let ip_rules = [
+            &self.ip_lists.all,
+            &self.ip_lists.status,
+            &self.ip_lists.login,
+        ];
+        [
+            &self.player_lists.allowlist_file,
+            &self.player_lists.denylist_file,
+        ]
+        .into_iter()
+        .chain(
+            ip_rules
+                .into_iter()
+                .flat_map(|rules| [&rules.allow_file, &rules.deny_file]),
+        )
+        .flatten()
+        .cloned()
+        .collect()
+    }
+
+    /// Every backend the proxy talks to, without duplicates.
+    pub fn backends(&self) -> Vec<BackendAddress> {
+        let mut backends = vec![self.target.clone()];
+        let others = self
+            .routes
+            .iter()
+            .map(|route| &route.target)
+            .chain(&self.players.online_backends);
+        for backend in others {
+            if !backends.contains(backend) {
+                backends.push(backend.clone());
+            }
+        }
+        backends
+    }
+}
+
+fn parse_target(field: &str, address: &str, port: u16) -> Result<BackendAddress, ConfigError> {
+    BackendAddress::parse(address, port).map_err(|source| ConfigError::InvalidTarget {
+        field: field.to_owned(),
+        address: address.to_owned(),
+        source,
+    })
+}
+
+fn validate_motd(field: &str, motd: &str) -> Result<(), ConfigError> {
+    let motd = serde_json::from_str::<serde_json::Value>(motd).map_err(|source| {
+        ConfigError::InvalidMotd {
+            field: field.to_owned(),
+            source,
+        }
+    })?;
+    validate_placeholders(field, &motd)
+}
+
+fn validate_placeholders(field: &str, component: &serde_json::Value) -> Result<(), ConfigError> {
+    match unknown_placeholder(component) {
+        Some(placeholder) => Err(ConfigError::UnknownPlaceholder {
+            field: field.to_owned(),
+            placeholder,
+        }),
+        None => Ok(()),
+    }
+}
+
+fn validate_pool(field: &str, pool: &MotdPool) -> Result<(), ConfigError> {
+    if pool.entries.is_empty() {
+        return Err(ConfigError::EmptyMotdPool {
+            field: field.to_owned(),
+        });
+    }
+    if pool.mode == MotdMode::Random && pool.entries.iter().all(|entry| entry.weight == 0) {
+        return Err(ConfigError::MissingMotdWeight {
+            field: field.to_owned(),
+        });
+    }
+    for (index, entry) in pool.entries.iter().enumerate() {
+        let field = format!("{}.entries[{}]", field, index);
+        validate_motd(&format!("{}.text", field), &entry.text)?;
+        for (name, time) in [("from", &entry.from), ("to", &entry.to)] {
+            if let Some(time) = time {
+                if parse_time(time).is_none() {
+                    return Err(ConfigError::InvalidTime {
+                        field: format!("{}.{}", field, name),
+                        time: time.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The config shared between every connection.
+///
+/// Connections take a snapshot with [`SharedConfig::get`] when they start, so replacing the config
+/// only affects new connections.
+#[derive(Debug, Clone)]
+pub struct SharedConfig(Arc<RwLock<Arc<Config>>>);
+
+impl SharedConfig {
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(config))))
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, config: Config) {
+        *self.0.write().unwrap() = Arc::new(config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_max_players_defaults_to_100() {
+        let config: Config = toml::from_str("[players]\nmax_mode = \"fixed\"").unwrap();
+        assert_eq!(config.players.max_mode, MaxPlayers::Fixed);
+        assert_eq!(config.players.max, 100);
+    }
+
+    #[test]
+    fn rejects_uuids_in_the_maintenance_allowlist() {
+        let mut config = Config {
+            target_ip: "127.0.0.1".to_owned(),
+            ..Config::default()
+        };
+        config.maintenance.allowlist = vec![
+            "Notch".to_owned(),
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5".to_owned(),
+        ];
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UuidEntry {
+                field: "maintenance.allowlist",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use async_std::io::stdin;
+use colored::Colorize;
+
+use crate::{config::SharedConfig, maintenance::Maintenance, stats::Stats};
+
+/// Reads commands typed into the terminal the proxy is running in.
+pub async fn read_commands(shared: SharedConfig, maintenance: Arc<Maintenance>, stats: Arc<Stats>) {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        // stdin is closed when running in the background, so there's nothing to read
+        match stdin().read_line(&mut line).await {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["maintenance", "on"] => {
+                maintenance.set(true);
+                println!("{}", "Maintenance is on.".yellow().bold());
+            }
+            ["maintenance", "off"] => {
+                maintenance.set(false);
+                println!("{}", "Maintenance is off.".green().bold());
+            }
+            ["maintenance"] => {
+                if maintenance.is_enabled(&shared.get()) {
+                    println!("{}", "Maintenance is on.".yellow().bold());
+                } else {
+                    println!("{}", "Maintenance is off.".green().bold());
+                }
+            }
+            ["stats"] => stats.print(),
+            _ => println!(
+                "{} {} {} {}",
+                "Unknown command, try".red(),
+                "maintenance [on|off]".cyan(),
+                "or".red(),
+                "stats".cyan()
+            ),
+        }
+    }
+}
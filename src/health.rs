@@ -0,0 +1,246 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_std::task::{sleep, spawn};
+use colored::Colorize;
+
+use crate::{
+    backend::{fetch_status, BackendAddress},
+    config::SharedConfig,
+    protocol::packets::{HandshakePacket, NextState},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// The backend hasn't been checked yet, or health checks are off.
+    Unknown,
+    Up,
+    Down,
+}
+
+#[derive(Debug)]
+struct BackendHealth {
+    health: Health,
+    since: Instant,
+    /// How many checks in a row have gone the other way.
+    streak: u32,
+}
+
+/// Keeps track of which backends are up by pinging them in the background.
+///
+/// A backend has to fail `fall` checks in a row to be marked as down, and pass `rise` checks in a
+/// row to be marked as up again, so one slow ping doesn't flip it back and forth.
+#[derive(Debug, Default)]
+pub struct HealthMonitor {
+    backends: Mutex<HashMap<BackendAddress, BackendHealth>>,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn health(&self, backend: &BackendAddress) -> Health {
+        self.backends
+            .lock()
+            .unwrap()
+            .get(backend)
+            .map(|state| state.health)
+            .unwrap_or(Health::Unknown)
+    }
+
+    /// Starts checking every backend in the config, and keeps doing so as the config changes.
+    pub async fn run(self: Arc<Self>, shared: SharedConfig) {
+        loop {
+            let config = shared.get();
+            if config.health_check.interval > 0 {
+                for backend in config.backends() {
+                    let mut backends = self.backends.lock().unwrap();
+                    if !backends.contains_key(&backend) {
+                        backends.insert(
+                            backend.clone(),
+                            BackendHealth {
+                                health: Health::Unknown,
+                                since: Instant::now(),
+                                streak: 0,
+                            },
+                        );
+                        spawn(self.clone().check(backend, shared.clone()));
+                    }
+                }
+            }
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    /// Checks one backend until it is removed from the config.
+    async fn check(self: Arc<Self>, backend: BackendAddress, shared: SharedConfig) {
+        loop {
+            let config = shared.get();
+            let settings = &config.health_check;
+            if settings.interval == 0 || !config.backends().contains(&backend) {
+                self.backends.lock().unwrap().remove(&backend);
+                return;
+            }
+
+            let probe = HandshakePacket {
+                protocol_version: -1,
+                server_address: backend.host.clone(),
+                server_port: backend.port,
+                next_state: NextState::Status,
+            };
+            let up = fetch_status(
+                &backend,
+                probe,
+                config.timeouts.backend_connect(),
+                config.timeouts.backend_read(),
+                config.packet_limits.backend_status(),
+            )
+            .await
+            .is_ok();
+            self.record(&backend, up, settings.rise, settings.fall);
+
+            sleep(Duration::from_millis(settings.interval)).await;
+        }
+    }
+
+    fn record(&self, backend: &BackendAddress, up: bool, rise: u32, fall: u32) {
+        let mut backends = self.backends.lock().unwrap();
+        let Some(state) = backends.get_mut(backend) else {
+            return;
+        };
+
+        let (health, needed) = if up {
+            (Health::Up, rise)
+        } else {
+            (Health::Down, fall)
+        };
+        if state.health == health {
+            state.streak = 0;
+            return;
+        }
+
+        state.streak += 1;
+        // the first check decides straight away, there's nothing to flap from
+        if state.health != Health::Unknown && state.streak < needed {
+            return;
+        }
+
+        let elapsed = state.since.elapsed();
+        let previous = state.health;
+        state.health = health;
+        state.since = Instant::now();
+        state.streak = 0;
+
+        match (previous, health) {
+            (Health::Unknown, Health::Up) => println!(
+                "{} {} {}",
+                "Backend".bright_blue(),
+                backend.to_string().bold(),
+                "is online.".green().bold()
+            ),
+            (Health::Unknown, _) => println!(
+                "{} {} {}",
+                "Backend".bright_blue(),
+                backend.to_string().bold(),
+                "is offline.".red().bold()
+            ),
+            (_, Health::Up) => println!(
+                "{} {} {} {}",
+                "Backend".bright_blue(),
+                backend.to_string().bold(),
+                "is back online".green().bold(),
+                format!("after being down for {}.", format_duration(elapsed)).bright_blue()
+            ),
+            _ => println!(
+                "{} {} {} {}",
+                "Backend".bright_blue(),
+                backend.to_string().bold(),
+                "went offline".red().bold(),
+                format!("after being up for {}.", format_duration(elapsed)).bright_blue()
+            ),
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RISE: u32 = 2;
+    const FALL: u32 = 3;
+
+    /// A monitor with one backend that hasn't been checked yet.
+    fn unchecked() -> (HealthMonitor, BackendAddress) {
+        let backend = BackendAddress {
+            host: "10.0.0.2".to_owned(),
+            port: 25565,
+        };
+        let monitor = HealthMonitor::new();
+        monitor.backends.lock().unwrap().insert(
+            backend.clone(),
+            BackendHealth {
+                health: Health::Unknown,
+                since: Instant::now(),
+                streak: 0,
+            },
+        );
+        (monitor, backend)
+    }
+
+    #[test]
+    fn the_first_check_decides_straight_away() {
+        let (monitor, backend) = unchecked();
+        assert_eq!(monitor.health(&backend), Health::Unknown);
+        monitor.record(&backend, false, RISE, FALL);
+        assert_eq!(monitor.health(&backend), Health::Down);
+
+        let (monitor, backend) = unchecked();
+        monitor.record(&backend, true, RISE, FALL);
+        assert_eq!(monitor.health(&backend), Health::Up);
+    }
+
+    #[test]
+    fn goes_down_after_fall_failures_in_a_row() {
+        let (monitor, backend) = unchecked();
+        monitor.record(&backend, true, RISE, FALL);
+
+        monitor.record(&backend, false, RISE, FALL);
+        monitor.record(&backend, false, RISE, FALL);
+        // a success in between starts the count again
+        monitor.record(&backend, true, RISE, FALL);
+        monitor.record(&backend, false, RISE, FALL);
+        monitor.record(&backend, false, RISE, FALL);
+        assert_eq!(monitor.health(&backend), Health::Up);
+
+        monitor.record(&backend, false, RISE, FALL);
+        assert_eq!(monitor.health(&backend), Health::Down);
+    }
+
+    #[test]
+    fn comes_back_up_after_rise_successes_in_a_row() {
+        let (monitor, backend) = unchecked();
+        monitor.record(&backend, false, RISE, FALL);
+
+        monitor.record(&backend, true, RISE, FALL);
+        assert_eq!(monitor.health(&backend), Health::Down);
+        monitor.record(&backend, false, RISE, FALL);
+        monitor.record(&backend, true, RISE, FALL);
+        assert_eq!(monitor.health(&backend), Health::Down);
+
+        monitor.record(&backend, true, RISE, FALL);
+        assert_eq!(monitor.health(&backend), Health::Up);
+    }
+}
@@ -0,0 +1,16 @@
+pub mod access;
+pub mod backend;
+pub mod cache;
+pub mod config;
+pub mod console;
+pub mod health;
+pub mod limit;
+pub mod maintenance;
+pub mod motd;
+pub mod protocol;
+pub mod proxy;
+pub mod reload;
+pub mod route;
+pub mod stats;
+pub mod status;
+pub mod template;
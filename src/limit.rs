@@ -0,0 +1,312 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+
+use crate::config::{RateLimit, RateLimitsConfig};
+
+/// How long each ip's state is kept after it was last used.
+const FORGET_AFTER: Duration = Duration::from_secs(60);
+/// At most this many rate limit and refusal messages are logged per [`LOG_WINDOW`].
+const LOG_BUDGET: u32 = 5;
+const LOG_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(limit: &RateLimit) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    fn take(&mut self, limit: &RateLimit) -> bool {
+        if limit.per_second <= 0.0 {
+            return true;
+        }
+
+        let elapsed = self.updated.elapsed().as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        self.updated = Instant::now();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Debug)]
+struct IpState {
+    status: Bucket,
+    login: Bucket,
+    connections: u32,
+    last_seen: Instant,
+}
+
+#[derive(Debug)]
+struct LogState {
+    window_start: Instant,
+    logged: u32,
+    suppressed: u32,
+}
+
+/// Limits how often each ip can ping and log in, and how many connections it can have open.
+///
+/// Pings and logins use token buckets: each ip can do `burst` of them at once, and gets
+/// `per_second` more back every second.
+#[derive(Debug)]
+pub struct RateLimiter {
+    ips: Mutex<HashMap<IpAddr, IpState>>,
+    pruned: Mutex<Instant>,
+    log: Mutex<LogState>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            ips: Mutex::default(),
+            pruned: Mutex::new(Instant::now()),
+            log: Mutex::new(LogState {
+                window_start: Instant::now(),
+                logged: 0,
+                suppressed: 0,
+            }),
+        }
+    }
+}
+
+/// Counts as one of an ip's open connections until it is dropped.
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    limiter: Arc<RateLimiter>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        if let Some(state) = self.limiter.ips.lock().unwrap().get_mut(&self.ip) {
+            state.connections = state.connections.saturating_sub(1);
+            state.last_seen = Instant::now();
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a new connection, unless the ip already has too many open.
+    pub fn connect(
+        self: &Arc<Self>,
+        ip: IpAddr,
+        limits: &RateLimitsConfig,
+    ) -> Option<ConnectionGuard> {
+        let mut ips = self.ips.lock().unwrap();
+        let mut pruned = self.pruned.lock().unwrap();
+        if pruned.elapsed() >= FORGET_AFTER {
+            ips.retain(|_, state| {
+                state.connections > 0 || state.last_seen.elapsed() < FORGET_AFTER
+            });
+            *pruned = Instant::now();
+        }
+
+        let state = ips.entry(ip).or_insert_with(|| IpState {
+            status: Bucket::new(&limits.status),
+            login: Bucket::new(&limits.login),
+            connections: 0,
+            last_seen: Instant::now(),
+        });
+        state.last_seen = Instant::now();
+        if limits.max_connections > 0 && state.connections >= limits.max_connections {
+            return None;
+        }
+        state.connections += 1;
+
+        Some(ConnectionGuard {
+            limiter: self.clone(),
+            ip,
+        })
+    }
+
+    /// Takes a token for a server list ping, returning false if the ip is pinging too often.
+    pub fn status(&self, ip: IpAddr, limits: &RateLimitsConfig) -> bool {
+        self.take(ip, |state| state.status.take(&limits.status))
+    }
+
+    /// Takes a token for a login, returning false if the ip is logging in too often.
+    pub fn login(&self, ip: IpAddr, limits: &RateLimitsConfig) -> bool {
+        self.take(ip, |state| state.login.take(&limits.login))
+    }
+
+    fn take(&self, ip: IpAddr, take: impl FnOnce(&mut IpState) -> bool) -> bool {
+        // every connection goes through `connect` first, so the ip is always there
+        self.ips.lock().unwrap().get_mut(&ip).is_none_or(take)
+    }
+
+    /// Logs that an ip hit a limit, unless too many of these have been logged lately.
+    ///
+    /// Floods come from a lot of connections at once, so this keeps them from flooding the log
+    /// too.
+    pub fn log(&self, ip: IpAddr, reason: &str) {
+        self.budgeted(|| {
+            println!(
+                "{} {} {}",
+                "Rate limited".bright_yellow(),
+                ip.to_string().bold(),
+                reason.yellow()
+            )
+        });
+    }
+
+    /// Logs that an ip was refused by the ip lists, sharing the budget of [`Self::log`].
+    pub fn refused(&self, ip: IpAddr) {
+        self.budgeted(|| {
+            println!(
+                "{} {}{}",
+                "Refused connection from".bright_yellow(),
+                ip.to_string().bold(),
+                ".".bright_yellow()
+            )
+        });
+    }
+
+    fn budgeted(&self, print: impl FnOnce()) {
+        let mut log = self.log.lock().unwrap();
+        if log.window_start.elapsed() >= LOG_WINDOW {
+            if log.suppressed > 0 {
+                println!(
+                    "{}",
+                    format!("{} more messages were hidden.", log.suppressed).yellow()
+                );
+            }
+            log.window_start = Instant::now();
+            log.logged = 0;
+            log.suppressed = 0;
+        }
+
+        if log.logged < LOG_BUDGET {
+            log.logged += 1;
+            print();
+        } else {
+            log.suppressed += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        per_second: 2.0,
+        burst: 3,
+    };
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last])
+    }
+
+    #[test]
+    fn buckets_run_out_after_the_burst() {
+        let mut bucket = Bucket::new(&LIMIT);
+        for _ in 0..3 {
+            assert!(bucket.take(&LIMIT));
+        }
+        assert!(!bucket.take(&LIMIT));
+    }
+
+    #[test]
+    fn buckets_refill_over_time() {
+        let mut bucket = Bucket::new(&LIMIT);
+        while bucket.take(&LIMIT) {}
+
+        // a second at 2 per second gives 2 back
+        bucket.updated -= Duration::from_secs(1);
+        assert!(bucket.take(&LIMIT));
+        assert!(bucket.take(&LIMIT));
+        assert!(!bucket.take(&LIMIT));
+
+        // but never more than the burst
+        bucket.updated -= Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(bucket.take(&LIMIT));
+        }
+        assert!(!bucket.take(&LIMIT));
+    }
+
+    #[test]
+    fn zero_per_second_turns_the_limit_off() {
+        let limit = RateLimit {
+            per_second: 0.0,
+            burst: 0,
+        };
+        let mut bucket = Bucket::new(&limit);
+        for _ in 0..1000 {
+            assert!(bucket.take(&limit));
+        }
+    }
+
+    #[test]
+    fn limits_each_ip_separately() {
+        let limiter = Arc::new(RateLimiter::new());
+        let limits = RateLimitsConfig {
+            login: LIMIT,
+            ..RateLimitsConfig::default()
+        };
+        let _first = limiter.connect(ip(1), &limits).unwrap();
+        let _second = limiter.connect(ip(2), &limits).unwrap();
+        for _ in 0..3 {
+            assert!(limiter.login(ip(1), &limits));
+        }
+        assert!(!limiter.login(ip(1), &limits));
+        assert!(limiter.login(ip(2), &limits));
+    }
+
+    #[test]
+    fn guards_give_connections_back_when_dropped() {
+        let limiter = Arc::new(RateLimiter::new());
+        let limits = RateLimitsConfig {
+            max_connections: 2,
+            ..RateLimitsConfig::default()
+        };
+        let first = limiter.connect(ip(1), &limits).unwrap();
+        let _second = limiter.connect(ip(1), &limits).unwrap();
+        assert!(limiter.connect(ip(1), &limits).is_none());
+        assert!(limiter.connect(ip(2), &limits).is_some());
+
+        drop(first);
+        assert_eq!(limiter.ips.lock().unwrap()[&ip(1)].connections, 1);
+        assert!(limiter.connect(ip(1), &limits).is_some());
+    }
+
+    #[test]
+    fn forgets_ips_that_have_gone_quiet() {
+        let limiter = Arc::new(RateLimiter::new());
+        let limits = RateLimitsConfig::default();
+        drop(limiter.connect(ip(1), &limits));
+        let _open = limiter.connect(ip(2), &limits).unwrap();
+        for state in limiter.ips.lock().unwrap().values_mut() {
+            state.last_seen -= FORGET_AFTER;
+        }
+        *limiter.pruned.lock().unwrap() -= FORGET_AFTER;
+
+        let _new = limiter.connect(ip(3), &limits).unwrap();
+        let ips = limiter.ips.lock().unwrap();
+        // ip 2 still has a connection open, so it's kept
+        assert!(!ips.contains_key(&ip(1)));
+        assert!(ips.contains_key(&ip(2)));
+        assert!(ips.contains_key(&ip(3)));
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::Mutex;
+
+use crate::config::Config;
+
+/// Whether the proxy is in maintenance.
+///
+/// This comes from the config, unless it has been switched on or off from the console, which lasts
+/// until the proxy restarts.
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    switch: Mutex<Option<bool>>,
+}
+
+impl Maintenance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self, config: &Config) -> bool {
+        self.switch
+            .lock()
+            .unwrap()
+            .unwrap_or(config.maintenance.enabled)
+    }
+
+    pub fn set(&self, enabled: bool) {
+        *self.switch.lock().unwrap() = Some(enabled);
+    }
+}
+
+/// Parses a UUID, with or without dashes.
+pub fn parse_uuid(uuid: &str) -> Option<u128> {
+    let hex = uuid.replace('-', "");
+    if hex.len() != 32 {
+        return None;
+    }
+    u128::from_str_radix(&hex, 16).ok()
+}
@@ -1,8 +1,160 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use chrono::{Local, Timelike};
 use colored::Colorize;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{config::Config, route::offline_motd_from};
+
+/// How a MOTD is picked from a [`MotdPool`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MotdMode {
+    /// Each status request gets the next MOTD in the list.
+    #[default]
+    Rotate,
+    /// MOTDs are picked at random, using their weights.
+    Random,
+    /// MOTDs are picked by the time of day, using their `from` and `to` times.
+    Schedule,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MotdEntry {
+    /// The MOTD, as a JSON text component.
+    pub text: String,
+    /// How likely this MOTD is to be picked in random mode.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// When this MOTD starts being shown in schedule mode, as local `HH:MM`.
+    pub from: Option<String>,
+    /// When this MOTD stops being shown in schedule mode, as local `HH:MM`.
+    pub to: Option<String>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl MotdEntry {
+    /// Whether the entry is scheduled for `minute`, counted from midnight.
+    ///
+    /// Entries without times are never scheduled, they're only used when nothing else is.
+    fn is_scheduled(&self, minute: u32) -> bool {
+        let (Some(from), Some(to)) = (
+            self.from.as_deref().and_then(parse_time),
+            self.to.as_deref().and_then(parse_time),
+        ) else {
+            return false;
+        };
+        if from <= to {
+            (from..to).contains(&minute)
+        } else {
+            // the window goes past midnight
+            minute >= from || minute < to
+        }
+    }
+}
+
+/// Parses a `HH:MM` time into minutes from midnight.
+pub fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// A list of MOTDs to pick from.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MotdPool {
+    #[serde(default)]
+    pub mode: MotdMode,
+    pub entries: Vec<MotdEntry>,
+    /// The next entry in rotate mode. This is shared between clones, so every route using the
+    /// same pool rotates together.
+    #[serde(skip)]
+    next: Arc<AtomicUsize>,
+}
+
+impl MotdPool {
+    /// A pool that always shows `text`.
+    pub fn single(text: &str) -> Self {
+        Self {
+            mode: MotdMode::Rotate,
+            entries: vec![MotdEntry {
+                text: text.to_owned(),
+                weight: default_weight(),
+                from: None,
+                to: None,
+            }],
+            next: Arc::default(),
+        }
+    }
+
+    /// Builds the offline pool by adding "(Offline)" to the end of every MOTD.
+    pub fn offline(&self) -> Self {
+        Self {
+            mode: self.mode,
+            entries: self
+                .entries
+                .iter()
+                .map(|entry| MotdEntry {
+                    text: offline_motd_from(&entry.text),
+                    ..entry.clone()
+                })
+                .collect(),
+            next: Arc::default(),
+        }
+    }
+
+    /// Picks the MOTD to show for one status request.
+    pub fn pick(&self) -> &str {
+        let index = match self.mode {
+            MotdMode::Rotate => self.next.fetch_add(1, Ordering::Relaxed) % self.entries.len(),
+            MotdMode::Random => {
+                // summed as u64 so that large weights can't overflow
+                let total: u64 = self.entries.iter().map(|entry| entry.weight as u64).sum();
+                let mut roll = rand::random_range(0..total.max(1));
+                self.entries
+                    .iter()
+                    .position(|entry| match roll.checked_sub(entry.weight as u64) {
+                        Some(rest) => {
+                            roll = rest;
+                            false
+                        }
+                        None => true,
+                    })
+                    .unwrap_or(0)
+            }
+            MotdMode::Schedule => {
+                let now = Local::now();
+                self.scheduled(now.hour() * 60 + now.minute())
+            }
+        };
+        &self.entries[index].text
+    }
 
-use crate::Config;
+    /// The entry to show at `minute` in schedule mode, or the first one without times if none
+    /// are scheduled then.
+    fn scheduled(&self, minute: u32) -> usize {
+        self.entries
+            .iter()
+            .position(|entry| entry.is_scheduled(minute))
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .position(|entry| entry.from.is_none() && entry.to.is_none())
+            })
+            .unwrap_or(0)
+    }
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct TextComponent {
     pub text: String,
     pub color: String,
@@ -13,6 +165,20 @@ struct TextComponent {
     pub obfuscated: bool,
 }
 
+impl Default for TextComponent {
+    fn default() -> Self {
+        Self {
+            text: "".to_owned(),
+            color: "gray".to_owned(),
+            bold: false,
+            italic: false,
+            underlined: false,
+            strikethrough: false,
+            obfuscated: false,
+        }
+    }
+}
+
 impl TextComponent {
     pub fn print(&self) {
         let text = &self.text;
@@ -54,78 +220,239 @@ impl TextComponent {
     }
 }
 
-pub fn print_motd(config: Config) {
-    let mut chars = config.motd.chars();
-    chars.next();
-    chars.next_back();
-    chars.next_back();
+/// Prints every MOTD in the config, so they can all be checked at once.
+pub fn print_motd(config: &Config) {
+    print_pool("", &config.motd_pool);
+    print_pool("Offline: ", &config.offline_motd_pool);
+    for route in &config.routes {
+        let hosts = format!("{}:", route.hosts.join(", "));
+        if route.motd.is_some() || route.motds.is_some() {
+            print_pool(&format!("{} ", hosts), &route.motd_pool);
+        }
+        if route.offline_motd.is_some() || route.offline_motds.is_some() {
+            print_pool(&format!("{} Offline: ", hosts), &route.offline_motd_pool);
+        }
+    }
+}
+
+fn print_pool(prefix: &str, pool: &MotdPool) {
+    for entry in &pool.entries {
+        print!("{}", prefix.bright_blue());
+        match (pool.mode, &entry.from, &entry.to) {
+            (MotdMode::Random, _, _) if pool.entries.len() > 1 => {
+                print!("{} ", format!("(weight {})", entry.weight).bright_blue())
+            }
+            (MotdMode::Schedule, Some(from), Some(to)) => {
+                print!("{} ", format!("({} - {})", from, to).bright_blue())
+            }
+            _ => {}
+        }
+        print_text(&entry.text);
+    }
+}
+
+/// Prints a JSON text component to the terminal in colour.
+pub fn print_text(text: &str) {
+    match serde_json::from_str::<Value>(text) {
+        Ok(motd) => {
+            let mut texts = vec![];
+            collect_components(&motd, &TextComponent::default(), &mut texts, true);
+            for text in texts {
+                text.print();
+            }
+        }
+        Err(_) => print!("{}", text),
+    }
+    println!();
+}
+
+/// Flattens a JSON text component into a list of styled texts.
+///
+/// Styles are passed down from each component to its `extra` children. Keys that can't be shown
+/// are skipped, with a warning if `warn` is set.
+fn collect_components(
+    value: &Value,
+    parent: &TextComponent,
+    texts: &mut Vec<TextComponent>,
+    warn: bool,
+) {
+    match value {
+        Value::String(string) => texts.push(TextComponent {
+            text: string.clone(),
+            ..parent.clone()
+        }),
+        Value::Array(values) => {
+            for value in values {
+                collect_components(value, parent, texts, warn);
+            }
+        }
+        Value::Object(map) => {
+            let mut text = TextComponent {
+                text: "".to_owned(),
+                ..parent.clone()
+            };
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("text", Value::String(string)) => text.text = string.clone(),
+                    ("color", Value::String(color)) => text.color = color.clone(),
+                    ("bold", Value::Bool(bold)) => text.bold = *bold,
+                    ("italic", Value::Bool(italic)) => text.italic = *italic,
+                    ("underlined", Value::Bool(underlined)) => text.underlined = *underlined,
+                    ("strikethrough", Value::Bool(strikethrough)) => {
+                        text.strikethrough = *strikethrough
+                    }
+                    ("obfuscated", Value::Bool(obfuscated)) => text.obfuscated = *obfuscated,
+                    ("extra", _) => {}
+                    _ if warn => println!("Didn't recognise key {} with value {}.", key, value),
+                    _ => {}
+                }
+            }
+            texts.push(text.clone());
+            if let Some(extra) = map.get("extra") {
+                text.text = "".to_owned();
+                collect_components(extra, &text, texts, warn);
+            }
+        }
+        _ => texts.push(TextComponent {
+            text: value.to_string(),
+            ..parent.clone()
+        }),
+    }
+}
+
+/// Turns a JSON text component into the `§` formatted text that clients before 1.7 expect.
+///
+/// This runs for every legacy ping, so keys it can't show are skipped without a warning.
+pub fn legacy_text(component: &Value) -> String {
     let mut texts = vec![];
-    for x in chars.as_str().split("},") {
-        let mut chars = x.chars();
-        chars.next();
-        let components = chars.as_str().split(",");
-        let mut text = TextComponent {
-            text: "".to_owned(),
-            color: "gray".to_owned(),
-            bold: false,
-            italic: false,
-            underlined: false,
-            strikethrough: false,
-            obfuscated: false,
+    collect_components(component, &TextComponent::default(), &mut texts, false);
+
+    let mut legacy = String::new();
+    for text in texts.into_iter().filter(|text| !text.text.is_empty()) {
+        let color = match text.color.as_str() {
+            "black" => '0',
+            "dark_blue" => '1',
+            "dark_green" => '2',
+            "dark_aqua" => '3',
+            "dark_red" => '4',
+            "dark_purple" => '5',
+            "gold" => '6',
+            "dark_gray" => '8',
+            "blue" => '9',
+            "green" => 'a',
+            "aqua" => 'b',
+            "red" => 'c',
+            "light_purple" => 'd',
+            "yellow" => 'e',
+            "white" => 'f',
+            _ => '7',
         };
-        for y in components {
-            let kv: Vec<&str> = y.split(":").collect();
-            let mut key = kv[0].chars();
-            key.next();
-            key.next_back();
-            let key = key.as_str();
-            let value = kv[1];
-
-            match key {
-                "text" => {
-                    let mut chars = value.chars();
-                    chars.next();
-                    chars.next_back();
-                    text.text = chars.as_str().to_string();
-                }
-                "color" => {
-                    let mut chars = value.chars();
-                    chars.next();
-                    chars.next_back();
-                    text.color = chars.as_str().to_string();
-                }
-                "bold" => match value {
-                    "true" => text.bold = true,
-                    "false" => text.bold = false,
-                    _ => println!("Unknown boolean value {}", value),
-                },
-                "italic" => match value {
-                    "true" => text.italic = true,
-                    "false" => text.italic = false,
-                    _ => println!("Unknown boolean value {}", value),
-                },
-                "underlined" => match value {
-                    "true" => text.underlined = true,
-                    "false" => text.underlined = false,
-                    _ => println!("Unknown boolean value {}", value),
-                },
-                "strikethrough" => match value {
-                    "true" => text.strikethrough = true,
-                    "false" => text.strikethrough = false,
-                    _ => println!("Unknown boolean value {}", value),
-                },
-                "obfuscated" => match value {
-                    "true" => text.obfuscated = true,
-                    "false" => text.obfuscated = false,
-                    _ => println!("Unknown boolean value {}", value),
-                },
-                _ => println!("Didn't recognise key {} with value {}.", key, value),
+        legacy.push('§');
+        legacy.push(color);
+        for (enabled, code) in [
+            (text.obfuscated, 'k'),
+            (text.bold, 'l'),
+            (text.strikethrough, 'm'),
+            (text.underlined, 'n'),
+            (text.italic, 'o'),
+        ] {
+            if enabled {
+                legacy.push('§');
+                legacy.push(code);
             }
         }
-        texts.push(text);
+        legacy.push_str(&text.text);
+    }
+    legacy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, weight: u32) -> MotdEntry {
+        MotdEntry {
+            text: text.to_owned(),
+            weight,
+            from: None,
+            to: None,
+        }
+    }
+
+    fn scheduled(text: &str, from: &str, to: &str) -> MotdEntry {
+        MotdEntry {
+            from: Some(from.to_owned()),
+            to: Some(to.to_owned()),
+            ..entry(text, 1)
+        }
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("00:00"), Some(0));
+        assert_eq!(parse_time("18:30"), Some(18 * 60 + 30));
+        assert_eq!(parse_time("23:59"), Some(23 * 60 + 59));
+        assert_eq!(parse_time("24:00"), None);
+        assert_eq!(parse_time("12:60"), None);
+        assert_eq!(parse_time("12"), None);
+        assert_eq!(parse_time("-1:00"), None);
+        assert_eq!(parse_time("noon"), None);
     }
-    for text in texts {
-        text.print();
+
+    #[test]
+    fn schedules_windows() {
+        let day = scheduled("day", "08:00", "20:00");
+        assert!(day.is_scheduled(8 * 60));
+        assert!(day.is_scheduled(19 * 60 + 59));
+        assert!(!day.is_scheduled(20 * 60));
+        assert!(!day.is_scheduled(7 * 60));
+
+        // windows can go past midnight
+        let night = scheduled("night", "22:00", "02:00");
+        assert!(night.is_scheduled(23 * 60));
+        assert!(night.is_scheduled(0));
+        assert!(night.is_scheduled(60));
+        assert!(!night.is_scheduled(2 * 60));
+        assert!(!night.is_scheduled(12 * 60));
+
+        assert!(!entry("always", 1).is_scheduled(12 * 60));
+    }
+
+    #[test]
+    fn falls_back_to_entries_without_times() {
+        let pool = MotdPool {
+            mode: MotdMode::Schedule,
+            entries: vec![
+                scheduled("night", "22:00", "02:00"),
+                entry("fallback", 1),
+                scheduled("morning", "06:00", "10:00"),
+            ],
+            next: Arc::default(),
+        };
+        assert_eq!(pool.scheduled(23 * 60), 0);
+        assert_eq!(pool.scheduled(7 * 60), 2);
+        assert_eq!(pool.scheduled(12 * 60), 1);
+
+        // or the first entry if every entry has times
+        let pool = MotdPool {
+            entries: vec![
+                scheduled("night", "22:00", "02:00"),
+                scheduled("morning", "06:00", "10:00"),
+            ],
+            ..pool
+        };
+        assert_eq!(pool.scheduled(12 * 60), 0);
+    }
+
+    #[test]
+    fn picks_with_weights_past_u32() {
+        let pool = MotdPool {
+            mode: MotdMode::Random,
+            entries: vec![entry("a", u32::MAX), entry("b", u32::MAX), entry("c", 0)],
+            next: Arc::default(),
+        };
+        for _ in 0..100 {
+            assert_ne!(pool.pick(), "c");
+        }
     }
-    print!("\n");
 }
@@ -0,0 +1,174 @@
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use aes::Aes128;
+use async_std::io::{Read, Write};
+use cfb8::{cipher::KeyIvInit, Decryptor, Encryptor};
+
+/// A stream encrypted with AES/CFB8, the way everything is encrypted after Encryption Response.
+///
+/// Both ways use the shared secret as the key and the IV, and keep going from where the last
+/// read or write left off. This implements `Read` and `Write`, so packets can be read and written
+/// with [`MinecraftStream`](super::stream::MinecraftStream) like on any other stream.
+///
+/// Like a `BufWriter`, writes can be buffered, so flush the stream to make sure they are sent.
+/// Writing packets flushes on its own.
+#[derive(Debug)]
+pub struct EncryptedStream<S> {
+    inner: S,
+    encryptor: Encryptor<Aes128>,
+    decryptor: Decryptor<Aes128>,
+    /// Bytes that have been encrypted but not written to `inner` yet.
+    pending: Vec<u8>,
+}
+
+impl<S> EncryptedStream<S> {
+    pub fn new(inner: S, shared_secret: &[u8; 16]) -> Self {
+        let (key, iv) = (shared_secret.into(), shared_secret.into());
+        Self {
+            inner,
+            encryptor: Encryptor::new(key, iv),
+            decryptor: Decryptor::new(key, iv),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Gets the stream back. Anything that hasn't been flushed yet is lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Write + Unpin> EncryptedStream<S> {
+    /// Writes out as much of `pending` as possible, finishing only once all of it is written.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while !self.pending.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: Read + Unpin> Read for EncryptedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let read = ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.decryptor.decrypt(&mut buf[..read]);
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<S: Write + Unpin> Write for EncryptedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        // the cipher moves on with every byte, so only take more once the last bytes are out
+        ready!(this.poll_pending(cx))?;
+
+        this.pending.extend_from_slice(buf);
+        this.encryptor.encrypt(&mut this.pending);
+        // if this can't finish now, the rest is written on the next write or flush
+        if let Poll::Ready(Err(err)) = this.poll_pending(cx) {
+            return Poll::Ready(Err(err));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::{
+        io::{ReadExt, WriteExt},
+        task::block_on,
+    };
+
+    use super::*;
+    use crate::protocol::{
+        packets::PingRequestPacket,
+        stream::{MemoryStream, MinecraftStream},
+    };
+
+    /// The AES key from NIST SP 800-38A, used as the shared secret.
+    const SECRET: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+    const PLAINTEXT: &[u8] = b"Hello from the other side of the encryption response!";
+    /// `PLAINTEXT` encrypted by OpenSSL's aes-128-cfb8, with `SECRET` as the key and IV.
+    const CIPHERTEXT: &[u8] = &[
+        0x37, 0x80, 0x99, 0x95, 0x1a, 0x87, 0x1f, 0x05, 0x88, 0x8e, 0xba, 0x4a, 0x40, 0x04, 0x26,
+        0xc6, 0xb8, 0xa6, 0xed, 0x77, 0x0a, 0x12, 0x2d, 0xdb, 0x5b, 0xf0, 0x9b, 0xe1, 0x1c, 0xbc,
+        0x6d, 0xc7, 0x64, 0xe3, 0xdd, 0x5c, 0xb8, 0xf4, 0x31, 0x69, 0xc6, 0x77, 0xe7, 0xcb, 0xd4,
+        0xfa, 0xed, 0xc2, 0xda, 0x58, 0x8d, 0xe1, 0x35,
+    ];
+    /// A ping with a payload of 42, encrypted the same way.
+    const ENCRYPTED_PING: &[u8] = &[0x76, 0x27, 0xae, 0x15, 0x62, 0xd0, 0xc6, 0x24, 0x89, 0xea];
+
+    #[test]
+    fn encrypts_like_the_reference() {
+        let mut stream = EncryptedStream::new(MemoryStream::new(vec![]), &SECRET);
+        block_on(async {
+            // split up, to check the cipher carries on between writes
+            for chunk in PLAINTEXT.chunks(7) {
+                stream.write_all(chunk).await.unwrap();
+            }
+            stream.flush().await.unwrap();
+        });
+        assert_eq!(stream.into_inner().output, CIPHERTEXT);
+    }
+
+    #[test]
+    fn decrypts_like_the_reference() {
+        let mut stream = EncryptedStream::new(MemoryStream::new(CIPHERTEXT.to_vec()), &SECRET);
+        let mut plaintext = vec![0; 5];
+        block_on(async {
+            stream.read_exact(&mut plaintext).await.unwrap();
+            stream.read_to_end(&mut plaintext).await.unwrap();
+        });
+        assert_eq!(plaintext, PLAINTEXT);
+    }
+
+    #[test]
+    fn reads_and_writes_packets() {
+        let mut stream = EncryptedStream::new(MemoryStream::new(vec![]), &SECRET);
+        block_on(stream.write_packet(&mut PingRequestPacket { payload: 42 })).unwrap();
+        assert_eq!(stream.get_ref().output, ENCRYPTED_PING);
+
+        let mut stream = EncryptedStream::new(MemoryStream::new(ENCRYPTED_PING.to_vec()), &SECRET);
+        let ping: PingRequestPacket = block_on(stream.read_packet()).unwrap();
+        assert_eq!(ping.payload, 42);
+    }
+}
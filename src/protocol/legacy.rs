@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use async_std::{
+    future::timeout,
+    io::{Read, ReadExt},
+};
+
+use super::read::MinecraftReadable;
+
+/// The first byte of a server list ping from a client older than 1.7.
+pub const LEGACY_PING: u8 = 0xFE;
+const KICK: u8 = 0xFF;
+const PLUGIN_MESSAGE: u8 = 0xFA;
+
+/// 1.4 and 1.5 clients stop after `0xFE 0x01`, so don't wait long for anything else.
+const FOLLOW_UP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// What a pre 1.7 client sent in its ping.
+///
+/// Only 1.6 clients send their protocol version and the address they connected with.
+#[derive(Debug, Default)]
+pub struct LegacyPing {
+    pub protocol_version: Option<i32>,
+    pub server_address: Option<String>,
+    pub server_port: Option<u16>,
+}
+
+/// The reply to a legacy ping, sent as a kick packet in the `§1` format.
+#[derive(Debug)]
+pub struct LegacyPingResponse {
+    pub protocol_version: i32,
+    pub version: String,
+    pub motd: String,
+    pub online: i32,
+    pub max: i32,
+}
+
+impl LegacyPingResponse {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let text = format!(
+            "§1\0{}\0{}\0{}\0{}\0{}",
+            self.protocol_version, self.version, self.motd, self.online, self.max
+        );
+        let text: Vec<u16> = text.encode_utf16().collect();
+
+        let mut bytes = vec![KICK];
+        bytes.extend_from_slice(&(text.len() as u16).to_be_bytes());
+        for unit in text {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+/// Reads a legacy ping, starting at the `0xFE` byte.
+pub async fn read_legacy_ping<R: Read + Unpin + Send + Sync>(
+    stream: &mut R,
+) -> Result<LegacyPing, Error> {
+    if u8::read_from(stream).await? != LEGACY_PING {
+        return Err(Error::msg("Expected a legacy ping"));
+    }
+
+    // anything after the first byte depends on the client's version
+    let Ok(Ok(_)) = timeout(FOLLOW_UP_TIMEOUT, u8::read_from(stream)).await else {
+        return Ok(LegacyPing::default());
+    };
+    let Ok(Ok(PLUGIN_MESSAGE)) = timeout(FOLLOW_UP_TIMEOUT, u8::read_from(stream)).await else {
+        return Ok(LegacyPing::default());
+    };
+
+    // 1.6 sends an `MC|PingHost` plugin message with the address it connected with
+    let channel_length = u16::read_from(stream).await?;
+    read_utf16(stream, channel_length).await?;
+    let _ = u16::read_from(stream).await?;
+    let protocol_version = u8::read_from(stream).await?;
+    let address_length = u16::read_from(stream).await?;
+    let server_address = read_utf16(stream, address_length).await?;
+    let server_port = i32::read_from(stream).await?;
+
+    Ok(LegacyPing {
+        protocol_version: Some(protocol_version as i32),
+        server_address: Some(server_address),
+        server_port: u16::try_from(server_port).ok(),
+    })
+}
+
+async fn read_utf16<R: Read + Unpin + Send + Sync>(
+    stream: &mut R,
+    length: u16,
+) -> Result<String, Error> {
+    let mut bytes = vec![0u8; length as usize * 2];
+    stream.read_exact(&mut bytes).await?;
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&units))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::{io::Cursor, task::block_on};
+
+    use super::*;
+
+    /// `§1\0127\01.21\0§aHi\03\020` as a kick packet.
+    const RESPONSE: &[u8] = &[
+        0xff, 0x00, 0x15, 0x00, 0xa7, 0x00, 0x31, 0x00, 0x00, 0x00, 0x31, 0x00, 0x32, 0x00, 0x37,
+        0x00, 0x00, 0x00, 0x31, 0x00, 0x2e, 0x00, 0x32, 0x00, 0x31, 0x00, 0x00, 0x00, 0xa7, 0x00,
+        0x61, 0x00, 0x48, 0x00, 0x69, 0x00, 0x00, 0x00, 0x33, 0x00, 0x00, 0x00, 0x32, 0x00, 0x30,
+    ];
+    /// A 1.6 ping for localhost:25565, with its `MC|PingHost` plugin message.
+    const PING_HOST: &[u8] = &[
+        0xfe, 0x01, 0xfa, 0x00, 0x0b, 0x00, 0x4d, 0x00, 0x43, 0x00, 0x7c, 0x00, 0x50, 0x00, 0x69,
+        0x00, 0x6e, 0x00, 0x67, 0x00, 0x48, 0x00, 0x6f, 0x00, 0x73, 0x00, 0x74, 0x00, 0x19, 0x4a,
+        0x00, 0x09, 0x00, 0x6c, 0x00, 0x6f, 0x00, 0x63, 0x00, 0x61, 0x00, 0x6c, 0x00, 0x68, 0x00,
+        0x6f, 0x00, 0x73, 0x00, 0x74, 0x00, 0x00, 0x63, 0xdd,
+    ];
+
+    #[test]
+    fn writes_responses() {
+        let response = LegacyPingResponse {
+            protocol_version: 127,
+            version: "1.21".to_owned(),
+            motd: "§aHi".to_owned(),
+            online: 3,
+            max: 20,
+        };
+        assert_eq!(response.to_bytes(), RESPONSE);
+    }
+
+    #[test]
+    fn reads_1_6_pings() {
+        let ping = block_on(read_legacy_ping(&mut Cursor::new(PING_HOST))).unwrap();
+        assert_eq!(ping.protocol_version, Some(74));
+        assert_eq!(ping.server_address.as_deref(), Some("localhost"));
+        assert_eq!(ping.server_port, Some(25565));
+    }
+
+    #[test]
+    fn reads_older_pings() {
+        let ping = block_on(read_legacy_ping(&mut Cursor::new(&[0xfe, 0x01]))).unwrap();
+        assert_eq!(ping.protocol_version, None);
+        assert_eq!(ping.server_address, None);
+    }
+}
@@ -1,3 +1,5 @@
+pub mod encryption;
+pub mod legacy;
 pub mod packets;
 pub mod read;
 pub mod stream;
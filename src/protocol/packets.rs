@@ -10,7 +10,7 @@ use super::read::MinecraftReadError;
 use super::write::MinecraftWriteable;
 use super::write::MinecraftWriteableVar;
 
-#[derive(MinecraftPacket, Debug, Default)]
+#[derive(MinecraftPacket, Debug, Clone, Default)]
 #[packet(id = 0)]
 pub struct HandshakePacket {
     #[var]
@@ -20,18 +20,13 @@ pub struct HandshakePacket {
     pub next_state: NextState,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
 pub enum NextState {
+    #[default]
     Status = 1,
     Login = 2,
 }
 
-impl Default for NextState {
-    fn default() -> Self {
-        NextState::Status
-    }
-}
-
 #[async_trait]
 impl<R: Read + Unpin + Send + Sync> MinecraftReadable<R> for NextState {
     async fn read_from(buffer: &mut R) -> Result<Self, Error> {
@@ -85,3 +80,19 @@ pub struct LoginStartPacket {
     pub name: String,
     pub uuid: u128,
 }
+
+#[derive(MinecraftPacket, Debug, Default)]
+#[packet(id = 0)]
+pub struct LoginDisconnectPacket {
+    /// The reason shown to the player, as a JSON text component.
+    pub reason: String,
+}
+
+/// Sent by the server before login success. Every packet after it, both ways, uses the
+/// compressed format with this threshold, or the normal format again if it is negative.
+#[derive(MinecraftPacket, Debug, Default)]
+#[packet(id = 3)]
+pub struct SetCompressionPacket {
+    #[var]
+    pub threshold: i32,
+}
@@ -1,9 +1,83 @@
 use anyhow::Error;
 use async_std::io::{Read, ReadExt};
 use async_trait::async_trait;
-use std::fmt::Debug;
+use std::{
+    cell::Cell,
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
 use thiserror::Error;
 
+/// The longest a string can be in bytes: 32767 characters, each up to 4 bytes, plus its length.
+pub const DEFAULT_MAX_STRING_LENGTH: u32 = 32767 * 4 + 3;
+/// The most elements a list read from the network can have.
+pub const DEFAULT_MAX_VEC_LENGTH: u32 = 4096;
+
+/// How long strings, in bytes, and lists read from the network can be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollectionLimits {
+    pub max_string_length: u32,
+    pub max_vec_length: u32,
+}
+
+impl CollectionLimits {
+    pub const DEFAULT: Self = Self {
+        max_string_length: DEFAULT_MAX_STRING_LENGTH,
+        max_vec_length: DEFAULT_MAX_VEC_LENGTH,
+    };
+}
+
+impl Default for CollectionLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+thread_local! {
+    /// The limits for the read being polled on this thread, see [`with_limits`].
+    static LIMITS: Cell<CollectionLimits> = const { Cell::new(CollectionLimits::DEFAULT) };
+}
+
+fn limits() -> CollectionLimits {
+    LIMITS.with(Cell::get)
+}
+
+/// Runs a read with `limits` for the strings and lists in it. Reads outside of this use
+/// [`CollectionLimits::DEFAULT`].
+///
+/// The readers are generic over what they read from, so the limits can't be passed to them.
+/// Instead they are set for the thread only while `future` is being polled, so reads on other
+/// connections, or for other packets on the same one, each keep their own.
+pub fn with_limits<F: Future + Unpin>(limits: CollectionLimits, future: F) -> WithLimits<F> {
+    WithLimits { limits, future }
+}
+
+/// The future returned by [`with_limits`].
+#[derive(Debug)]
+pub struct WithLimits<F> {
+    limits: CollectionLimits,
+    future: F,
+}
+
+impl<F: Future + Unpin> Future for WithLimits<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        struct Restore(CollectionLimits);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                LIMITS.with(|limits| limits.set(self.0));
+            }
+        }
+
+        let this = self.get_mut();
+        let _restore = Restore(LIMITS.with(|limits| limits.replace(this.limits)));
+        Pin::new(&mut this.future).poll(cx)
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum MinecraftReadError {
     #[error("Invalid VarInt")]
@@ -12,10 +86,43 @@ pub enum MinecraftReadError {
     InvalidVarLong,
     #[error("Invalid String length; max length is {max_length} but got {length}")]
     StringTooLong { length: u32, max_length: u32 },
+    #[error("Invalid list length; max length is {max_length} but got {length}")]
+    VecTooLong { length: u32, max_length: u32 },
+    #[error("Invalid packet length; max length is {max_length} but got {length}")]
+    FrameTooLong { length: u32, max_length: u32 },
+    #[error("Invalid uncompressed length; max length is {max_length} but got {length}")]
+    UncompressedTooLong { length: u32, max_length: u32 },
+    #[error("Badly compressed packet; {length} bytes is below the threshold of {threshold}")]
+    CompressedBelowThreshold { length: u32, threshold: u32 },
+    #[error("Badly compressed packet; expected {expected} bytes but got {actual}")]
+    WrongUncompressedLength { expected: u32, actual: u32 },
+    #[error("Invalid length; it can't be negative but got {0}")]
+    NegativeLength(i32),
     #[error("Invalid Enum variant")]
     UnexpectedEnumVariant,
 }
 
+/// Checks a length read from the network before anything is allocated for it.
+pub(crate) fn check_length(
+    length: i32,
+    max_length: u32,
+    too_long: fn(u32, u32) -> MinecraftReadError,
+) -> Result<usize, MinecraftReadError> {
+    if length < 0 {
+        return Err(MinecraftReadError::NegativeLength(length));
+    }
+    if length as u32 > max_length {
+        return Err(too_long(length as u32, max_length));
+    }
+    Ok(length as usize)
+}
+
+fn check_vec_length(length: i32) -> Result<usize, MinecraftReadError> {
+    check_length(length, limits().max_vec_length, |length, max_length| {
+        MinecraftReadError::VecTooLong { length, max_length }
+    })
+}
+
 #[async_trait]
 pub trait MinecraftReadable<R: Read + Unpin + Send + Sync>: Debug + Send + Sync {
     async fn read_from(buffer: &mut R) -> Result<Self, Error>
@@ -32,11 +139,14 @@ pub trait MinecraftReadableVar<R: Read + Unpin + Send + Sync>: Debug + Send + Sy
 
 #[async_trait]
 impl<R: Read + Unpin + Send + Sync> MinecraftReadableVar<R> for i32 {
+    /// Reads up to 5 bytes, 7 bits at a time with the least significant group first. Negative
+    /// numbers always take all 5.
     async fn read_var_from(buffer: &mut R) -> Result<Self, Error> {
         let mut buf = [0u8; 1];
         let mut ans = 0;
 
-        for i in 0..4 {
+        // bits past the 32nd are dropped, like the vanilla server does
+        for i in 0..5 {
             buffer.read_exact(&mut buf).await?;
             ans |= (buf[0] as i32 & 0x7F) << (7 * i);
             if buf[0] & 0x80 == 0 {
@@ -50,11 +160,12 @@ impl<R: Read + Unpin + Send + Sync> MinecraftReadableVar<R> for i32 {
 
 #[async_trait]
 impl<R: Read + Unpin + Send + Sync> MinecraftReadableVar<R> for i64 {
+    /// Reads up to 10 bytes, the same way as a VarInt.
     async fn read_var_from(buffer: &mut R) -> Result<Self, Error> {
         let mut buf = [0];
         let mut ans: i64 = 0;
 
-        for i in 0..8 {
+        for i in 0..10 {
             buffer.read_exact(&mut buf).await?;
             ans |= (buf[0] as i64 & 0x7F) << (7 * i);
             if buf[0] & 0x80 == 0 {
@@ -84,7 +195,7 @@ impl<R: Read + Unpin + Send + Sync, T: MinecraftReadableVar<R> + Send> Minecraft
     for Vec<T>
 {
     async fn read_var_from(buffer: &mut R) -> Result<Self, Error> {
-        let length = i32::read_var_from(buffer).await?;
+        let length = check_vec_length(i32::read_var_from(buffer).await?)?;
         let mut contents = Vec::new();
         for _ in 0..length {
             contents.push(T::read_var_from(buffer).await?);
@@ -213,16 +324,12 @@ impl<R: Read + Unpin + Send + Sync> MinecraftReadable<R> for f64 {
 #[async_trait]
 impl<R: Read + Unpin + Send + Sync> MinecraftReadable<R> for String {
     async fn read_from(buffer: &mut R) -> Result<Self, Error> {
-        let length = i32::read_var_from(buffer).await?;
-
-        if length > 32767 * 4 + 3 {
-            return Err(MinecraftReadError::StringTooLong {
-                length: length as u32,
-                max_length: 32767 * 4 + 3,
-            }
-            .into());
-        }
-        let mut buf = vec![0; length as usize];
+        let length = check_length(
+            i32::read_var_from(buffer).await?,
+            limits().max_string_length,
+            |length, max_length| MinecraftReadError::StringTooLong { length, max_length },
+        )?;
+        let mut buf = vec![0; length];
         buffer.read_exact(&mut buf).await?;
 
         Ok(String::from_utf8(buf)?)
@@ -245,7 +352,7 @@ impl<R: Read + Unpin + Send + Sync, T: MinecraftReadable<R> + Send> MinecraftRea
     for Vec<T>
 {
     async fn read_from(buffer: &mut R) -> Result<Self, Error> {
-        let length = i32::read_var_from(buffer).await?;
+        let length = check_vec_length(i32::read_var_from(buffer).await?)?;
         let mut contents = Vec::new();
         for _ in 0..length {
             contents.push(T::read_from(buffer).await?);
@@ -263,9 +370,85 @@ impl<
 {
     async fn read_from(buffer: &mut R) -> Result<Self, Error> {
         let mut contents = [T::default(); N];
-        for i in 0..N {
-            contents[i] = T::read_from(buffer).await?;
+        for item in contents.iter_mut() {
+            *item = T::read_from(buffer).await?;
         }
         Ok(contents)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_std::{io::Cursor, task::block_on};
+
+    use super::*;
+    use crate::protocol::write::MinecraftWriteableVar;
+
+    fn read<T: MinecraftReadable<Cursor<Vec<u8>>>>(length: i32) -> Result<T, Error> {
+        block_on(async {
+            let mut bytes = Cursor::new(Vec::new());
+            length.write_var_to(&mut bytes).await?;
+            bytes.set_position(0);
+            T::read_from(&mut bytes).await
+        })
+    }
+
+    #[test]
+    fn rejects_negative_lengths() {
+        assert!(matches!(
+            check_length(-1, 16, |length, max_length| {
+                MinecraftReadError::VecTooLong { length, max_length }
+            }),
+            Err(MinecraftReadError::NegativeLength(-1))
+        ));
+        assert!(matches!(
+            check_length(i32::MIN, 16, |length, max_length| {
+                MinecraftReadError::VecTooLong { length, max_length }
+            }),
+            Err(MinecraftReadError::NegativeLength(i32::MIN))
+        ));
+    }
+
+    #[test]
+    fn rejects_var_numbers_that_never_end() {
+        let mut bytes = Cursor::new(vec![0x80; 6]);
+        let err = block_on(i32::read_var_from(&mut bytes)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MinecraftReadError::InvalidVarInt)
+        ));
+
+        let mut bytes = Cursor::new(vec![0x80; 11]);
+        let err = block_on(i64::read_var_from(&mut bytes)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MinecraftReadError::InvalidVarLong)
+        ));
+    }
+
+    #[test]
+    fn rejects_long_strings_before_reading_them() {
+        let err = read::<String>(DEFAULT_MAX_STRING_LENGTH as i32 + 1).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MinecraftReadError::StringTooLong {
+                max_length: DEFAULT_MAX_STRING_LENGTH,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_long_lists_before_reading_them() {
+        let err = read::<Vec<u8>>(100_000_000).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MinecraftReadError::VecTooLong {
+                length: 100_000_000,
+                max_length: DEFAULT_MAX_VEC_LENGTH,
+            })
+        ));
+        // an empty list is fine
+        assert_eq!(read::<Vec<u8>>(0).unwrap(), Vec::<u8>::new());
+    }
+}
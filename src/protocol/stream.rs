@@ -1,7 +1,7 @@
-use std::{fmt::Debug, pin::Pin, task::Poll};
+use std::{fmt::Debug, io::Read as _, pin::Pin, task::Poll};
 
 use super::{
-    read::MinecraftReadable,
+    read::{check_length, with_limits, CollectionLimits, MinecraftReadError, MinecraftReadable},
     write::{MinecraftWriteable, MinecraftWriteableVar},
 };
 use crate::protocol::read::MinecraftReadableVar;
@@ -11,6 +11,7 @@ use async_std::{
     task::{block_on, Context},
 };
 use async_trait::async_trait;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
 
 #[derive(Debug)]
 pub struct Cursor(async_std::io::Cursor<Vec<u8>>);
@@ -61,9 +62,7 @@ impl Write for Cursor {
 
 impl Cursor {
     pub fn new(inner: Vec<u8>) -> Cursor {
-        Self {
-            0: async_std::io::Cursor::new(inner),
-        }
+        Self(async_std::io::Cursor::new(inner))
     }
 
     pub fn into_inner(self) -> Vec<u8> {
@@ -87,29 +86,156 @@ impl Cursor {
     }
 }
 
+/// A stream that reads from a fixed buffer and keeps everything written to it, for running
+/// connections without a socket.
+#[derive(Debug)]
+pub struct MemoryStream {
+    input: async_std::io::Cursor<Vec<u8>>,
+    pub output: Vec<u8>,
+}
+
+impl MemoryStream {
+    pub fn new(input: Vec<u8>) -> Self {
+        Self {
+            input: async_std::io::Cursor::new(input),
+            output: Vec::new(),
+        }
+    }
+}
+
+impl Read for MemoryStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        Pin::new(&mut self.get_mut().input).poll_read(cx, buf)
+    }
+}
+
+impl Write for MemoryStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        self.get_mut().output.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The longest packet the vanilla server accepts, in bytes.
+pub const MAX_PACKET_LENGTH: u32 = 2097151;
+/// The longest a compressed packet can be once it is decompressed, in bytes.
+pub const MAX_UNCOMPRESSED_LENGTH: u32 = 8388608;
+
+/// How packets are framed on a stream.
+///
+/// Connections can use different framing in each state, e.g. to only allow short packets before
+/// the client has logged in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Framing {
+    /// The longest packet that will be read, in bytes. Longer packets are rejected before
+    /// anything is allocated for them.
+    pub max_length: u32,
+    /// Packets at least this long are compressed. `None` until the server sends Set Compression,
+    /// which switches both ways to the compressed format.
+    pub compression_threshold: Option<u32>,
+    /// How long strings and lists in packets can be.
+    pub limits: CollectionLimits,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Self {
+            max_length: MAX_PACKET_LENGTH,
+            compression_threshold: None,
+            limits: CollectionLimits::DEFAULT,
+        }
+    }
+}
+
+impl Framing {
+    pub fn limited(max_length: u32) -> Self {
+        Self {
+            max_length,
+            ..Self::default()
+        }
+    }
+
+    /// Applies the threshold from a Set Compression packet. Negative thresholds turn compression
+    /// off again.
+    pub fn set_compression(&mut self, threshold: i32) {
+        self.compression_threshold = u32::try_from(threshold).ok();
+    }
+}
+
 pub trait MinecraftPacket: MinecraftReadable<Cursor> + MinecraftWriteable<Cursor> {
     fn get_id() -> i32;
 }
 
 #[async_trait]
 pub trait MinecraftStream<S: Read + Write + Send + Sync + Unpin> {
+    /// Reads one packet's id and the rest of its data, whatever packet it is.
+    async fn read_frame(&mut self, framing: &Framing) -> Result<(i32, Cursor), Error>;
     async fn read_packet<R: MinecraftPacket + Send>(&mut self) -> Result<R, Error>;
+    async fn read_packet_framed<R: MinecraftPacket + Send>(
+        &mut self,
+        framing: &Framing,
+    ) -> Result<R, Error>;
     async fn write_packet<R: MinecraftPacket + Send>(
         &mut self,
         packet: &mut R,
     ) -> Result<(), Error>;
+    async fn write_packet_framed<R: MinecraftPacket + Send>(
+        &mut self,
+        framing: &Framing,
+        packet: &mut R,
+    ) -> Result<(), Error>;
 }
 
 #[async_trait]
 impl<S: Read + Write + Send + Sync + Unpin + Debug> MinecraftStream<S> for S {
-    async fn read_packet<R: MinecraftPacket + Send>(&mut self) -> Result<R, Error> {
-        let length = i32::read_var_from(self).await?;
+    async fn read_frame(&mut self, framing: &Framing) -> Result<(i32, Cursor), Error> {
+        let length = check_length(
+            i32::read_var_from(self).await?,
+            framing.max_length,
+            |length, max_length| MinecraftReadError::FrameTooLong { length, max_length },
+        )?;
 
-        let mut buffer = vec![0u8; length as usize];
+        let mut buffer = vec![0u8; length];
         self.read_exact(&mut buffer).await?;
         let mut cursor = Cursor::new(buffer);
 
+        if let Some(threshold) = framing.compression_threshold {
+            // 0 means the packet was too short to be compressed
+            let data_length = i32::read_var_from(&mut cursor).await?;
+            if data_length != 0 {
+                cursor = decompress(&cursor, data_length, threshold)?;
+            }
+        }
+
         let id = i32::read_var_from(&mut cursor).await?;
+        Ok((id, cursor))
+    }
+
+    async fn read_packet<R: MinecraftPacket + Send>(&mut self) -> Result<R, Error> {
+        self.read_packet_framed(&Framing::default()).await
+    }
+
+    async fn read_packet_framed<R: MinecraftPacket + Send>(
+        &mut self,
+        framing: &Framing,
+    ) -> Result<R, Error> {
+        let (id, mut cursor) = self.read_frame(framing).await?;
 
         if id != R::get_id() {
             return Err(Error::msg(format!(
@@ -119,23 +245,269 @@ impl<S: Read + Write + Send + Sync + Unpin + Debug> MinecraftStream<S> for S {
             )));
         }
 
-        R::read_from(&mut cursor).await
+        with_limits(framing.limits, R::read_from(&mut cursor)).await
+    }
+
+    async fn write_packet<R: MinecraftPacket + Send>(
+        &mut self,
+        packet: &mut R,
+    ) -> Result<(), Error> {
+        self.write_packet_framed(&Framing::default(), packet).await
     }
 
-    async fn write_packet<R: MinecraftPacket + Send + ?Sized>(
+    async fn write_packet_framed<R: MinecraftPacket + Send>(
         &mut self,
+        framing: &Framing,
         packet: &mut R,
     ) -> Result<(), Error> {
-        let mut data = Cursor::new(Vec::new());
-        let mut id = Cursor::new(Vec::new());
+        let mut body = Cursor::new(Vec::new());
+        R::get_id().write_var_to(&mut body).await?;
+        packet.write_to(&mut body).await?;
+        let body = body.into_inner();
 
-        packet.write_to(&mut data).await?;
-        R::get_id().write_var_to(&mut id).await?;
+        let mut frame = Cursor::new(Vec::new());
+        match framing.compression_threshold {
+            None => {
+                (body.len() as i32).write_var_to(&mut frame).await?;
+                frame.write_all(&body).await?;
+            }
+            Some(threshold) => {
+                let (data_length, data) = if body.len() >= threshold as usize {
+                    (body.len() as i32, compress(&body)?)
+                } else {
+                    (0, body)
+                };
+                let mut header = Cursor::new(Vec::new());
+                data_length.write_var_to(&mut header).await?;
 
-        i32::write_var_to(&((data.get_ref().len() + id.get_ref().len()) as i32), self).await?;
-        self.write_all(id.get_ref()).await?;
-        self.write_all(data.get_ref()).await?;
+                ((header.get_ref().len() + data.len()) as i32)
+                    .write_var_to(&mut frame)
+                    .await?;
+                frame.write_all(header.get_ref()).await?;
+                frame.write_all(&data).await?;
+            }
+        }
+        self.write_all(frame.get_ref()).await?;
+        // streams like `EncryptedStream` buffer writes
+        self.flush().await?;
 
         Ok(())
     }
 }
+
+fn compress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    std::io::Write::write_all(&mut encoder, data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Decompresses the rest of a frame, which should come to `data_length` bytes.
+fn decompress(frame: &Cursor, data_length: i32, threshold: u32) -> Result<Cursor, Error> {
+    let data_length = check_length(
+        data_length,
+        MAX_UNCOMPRESSED_LENGTH,
+        |length, max_length| MinecraftReadError::UncompressedTooLong { length, max_length },
+    )?;
+    // the vanilla server doesn't compress anything below the threshold, so neither should clients
+    if data_length < threshold as usize {
+        return Err(MinecraftReadError::CompressedBelowThreshold {
+            length: data_length as u32,
+            threshold,
+        }
+        .into());
+    }
+
+    let compressed = &frame.get_ref()[frame.position() as usize..];
+    let mut data = Vec::with_capacity(data_length);
+    // read one byte more than expected, so data that is too long is caught
+    ZlibDecoder::new(compressed)
+        .take(data_length as u64 + 1)
+        .read_to_end(&mut data)?;
+    if data.len() != data_length {
+        return Err(MinecraftReadError::WrongUncompressedLength {
+            expected: data_length as u32,
+            actual: data.len() as u32,
+        }
+        .into());
+    }
+
+    Ok(Cursor::new(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::task::block_on;
+
+    use super::*;
+    use crate::protocol::packets::{PingRequestPacket, StatusResponsePacket};
+
+    const STATUS: &str = r#"{"description":"A Minecraft Server A Minecraft Server A Minecraft Server A Minecraft Server "}"#;
+    /// A status response compressed by zlib, as a server with a threshold of 64 would send it.
+    const COMPRESSED_STATUS: &[u8] = &[
+        0x32, 0x60, 0x78, 0x9c, 0x63, 0x88, 0xab, 0x56, 0x4a, 0x49, 0x2d, 0x4e, 0x2e, 0xca, 0x2c,
+        0x28, 0xc9, 0xcc, 0xcf, 0x53, 0xb2, 0x52, 0x72, 0x54, 0xf0, 0xcd, 0xcc, 0x4b, 0x4d, 0x2e,
+        0x4a, 0x4c, 0x2b, 0x51, 0x08, 0x4e, 0x2d, 0x2a, 0x4b, 0x2d, 0x52, 0x20, 0x5b, 0x48, 0xa9,
+        0x16, 0x00, 0x4d, 0xe3, 0x21, 0x81,
+    ];
+    /// A ping that is too short to be compressed, with its data length of 0.
+    const UNCOMPRESSED_PING: &[u8] = &[0x0a, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 42];
+
+    fn compressed(threshold: i32) -> Framing {
+        let mut framing = Framing::default();
+        framing.set_compression(threshold);
+        framing
+    }
+
+    fn frame(length: i32, data: &[u8]) -> Cursor {
+        block_on(async {
+            let mut bytes = Cursor::new(Vec::new());
+            length.write_var_to(&mut bytes).await.unwrap();
+            bytes.write_all(data).await.unwrap();
+            bytes.set_position(0);
+            bytes
+        })
+    }
+
+    #[test]
+    fn rejects_long_frames_before_reading_them() {
+        let mut stream = frame(100_000_000, &[]);
+        let err = block_on(stream.read_packet::<PingRequestPacket>()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MinecraftReadError::FrameTooLong {
+                length: 100_000_000,
+                max_length: MAX_PACKET_LENGTH,
+            })
+        ));
+
+        let mut stream = frame(10, &[]);
+        let err = block_on(stream.read_frame(&Framing::limited(9))).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MinecraftReadError::FrameTooLong {
+                length: 10,
+                max_length: 9,
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_negative_frame_lengths() {
+        let mut stream = frame(-1, &[]);
+        let err = block_on(stream.read_packet::<PingRequestPacket>()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MinecraftReadError::NegativeLength(-1))
+        ));
+    }
+
+    #[test]
+    fn reads_frames_within_the_limit() {
+        let mut stream = frame(9, &[1, 0, 0, 0, 0, 0, 0, 0, 42]);
+        let ping: PingRequestPacket =
+            block_on(stream.read_packet_framed(&Framing::limited(9))).unwrap();
+        assert_eq!(ping.payload, 42);
+    }
+
+    #[test]
+    fn limits_strings_for_each_read() {
+        let framing = Framing {
+            limits: CollectionLimits {
+                max_string_length: 16,
+                ..CollectionLimits::DEFAULT
+            },
+            ..Framing::default()
+        };
+        let mut stream = Cursor::new(Vec::new());
+        let mut status = StatusResponsePacket {
+            response: STATUS.to_owned(),
+        };
+        block_on(async {
+            stream.write_packet(&mut status).await.unwrap();
+            stream.write_packet(&mut status).await.unwrap();
+        });
+        stream.set_position(0);
+
+        let err =
+            block_on(stream.read_packet_framed::<StatusResponsePacket>(&framing)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MinecraftReadError::StringTooLong { max_length: 16, .. })
+        ));
+
+        // the next read, without the limits, isn't held to them
+        let status: StatusResponsePacket = block_on(stream.read_packet()).unwrap();
+        assert_eq!(status.response, STATUS);
+    }
+
+    #[test]
+    fn reads_compressed_frames() {
+        let mut stream = Cursor::new(COMPRESSED_STATUS.to_vec());
+        let status: StatusResponsePacket =
+            block_on(stream.read_packet_framed(&compressed(64))).unwrap();
+        assert_eq!(status.response, STATUS);
+
+        let mut stream = Cursor::new(UNCOMPRESSED_PING.to_vec());
+        let ping: PingRequestPacket =
+            block_on(stream.read_packet_framed(&compressed(256))).unwrap();
+        assert_eq!(ping.payload, 42);
+    }
+
+    #[test]
+    fn writes_compressed_frames() {
+        let mut stream = Cursor::new(Vec::new());
+        block_on(
+            stream.write_packet_framed(&compressed(256), &mut PingRequestPacket { payload: 42 }),
+        )
+        .unwrap();
+        assert_eq!(stream.get_ref(), UNCOMPRESSED_PING);
+
+        let mut stream = Cursor::new(Vec::new());
+        block_on(stream.write_packet_framed(
+            &compressed(64),
+            &mut StatusResponsePacket {
+                response: STATUS.to_owned(),
+            },
+        ))
+        .unwrap();
+        // the uncompressed length, after the packet length
+        assert_eq!(stream.get_ref()[1], 0x60);
+
+        stream.set_position(0);
+        let status: StatusResponsePacket =
+            block_on(stream.read_packet_framed(&compressed(64))).unwrap();
+        assert_eq!(status.response, STATUS);
+    }
+
+    #[test]
+    fn rejects_badly_compressed_frames() {
+        let mut stream = Cursor::new(COMPRESSED_STATUS.to_vec());
+        let err = block_on(stream.read_frame(&compressed(256))).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MinecraftReadError::CompressedBelowThreshold {
+                length: 96,
+                threshold: 256,
+            })
+        ));
+
+        for (data_length, actual) in [(0x5f, 96), (0x61, 96)] {
+            let mut frame = COMPRESSED_STATUS.to_vec();
+            frame[1] = data_length;
+            let err = block_on(Cursor::new(frame).read_frame(&compressed(64))).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref(),
+                Some(MinecraftReadError::WrongUncompressedLength { expected, actual: got })
+                    if *expected == data_length as u32 && *got == actual
+            ));
+        }
+    }
+
+    #[test]
+    fn negative_thresholds_turn_compression_off() {
+        let mut framing = compressed(256);
+        assert_eq!(framing.compression_threshold, Some(256));
+        framing.set_compression(-1);
+        assert_eq!(framing, Framing::default());
+    }
+}
@@ -29,25 +29,9 @@ pub trait MinecraftWriteableVar<W: Write + Unpin + Send + Sync>: Debug + Send +
 #[async_trait]
 impl<W: Write + Unpin + Send + Sync> MinecraftWriteableVar<W> for i32 {
     async fn write_var_to(&self, buffer: &mut W) -> Result<(), Error> {
-        if self == &0i32 {
-            buffer.write(&[0u8; 1]).await?;
-            return Ok(());
-        }
-
-        let mut buf = [0];
-        let mut value = self.clone();
-
-        while value != 0 {
-            buf[0] = (value & 0b0111_1111) as u8;
-            value = (value >> 7) & (i32::max_value() >> 6);
-
-            if value != 0 {
-                buf[0] |= 0b1000_0000;
-            }
-
-            buffer.write(&mut buf).await?;
-        }
-
+        let mut buf = [0u8; 5];
+        let length = encode_var(*self as u32 as u64, &mut buf);
+        buffer.write_all(&buf[..length]).await?;
         Ok(())
     }
 }
@@ -55,21 +39,27 @@ impl<W: Write + Unpin + Send + Sync> MinecraftWriteableVar<W> for i32 {
 #[async_trait]
 impl<W: Write + Unpin + Send + Sync> MinecraftWriteableVar<W> for i64 {
     async fn write_var_to(&self, buffer: &mut W) -> Result<(), Error> {
-        let mut buf = [0];
-        let mut value = self.clone();
-
-        while value != 0 {
-            buf[0] = (value & 0b0111_1111) as u8;
-            value = (value >> 7) & (i64::max_value() >> 6);
-
-            if value != 0 {
-                buf[0] |= 0b1000_0000;
-            }
+        let mut buf = [0u8; 10];
+        let length = encode_var(*self as u64, &mut buf);
+        buffer.write_all(&buf[..length]).await?;
+        Ok(())
+    }
+}
 
-            buffer.write(&mut buf).await?;
+/// Encodes a number 7 bits at a time, least significant group first, with the top bit of each
+/// byte set if there are more to come. Negative numbers are encoded as their unsigned bits, so
+/// they take up every byte.
+fn encode_var(mut value: u64, buf: &mut [u8]) -> usize {
+    let mut length = 0;
+    loop {
+        let byte = (value & 0b0111_1111) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[length] = byte;
+            return length + 1;
         }
-
-        Ok(())
+        buf[length] = byte | 0b1000_0000;
+        length += 1;
     }
 }
 
@@ -222,3 +212,97 @@ impl<W: Write + Unpin + Send + Sync, T: MinecraftWriteable<W>> MinecraftWriteabl
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_std::{io::Cursor, task::block_on};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::protocol::read::MinecraftReadableVar;
+
+    const VAR_INTS: &[(i32, &[u8])] = &[
+        (0, &[0x00]),
+        (1, &[0x01]),
+        (2, &[0x02]),
+        (127, &[0x7f]),
+        (128, &[0x80, 0x01]),
+        (255, &[0xff, 0x01]),
+        (25565, &[0xdd, 0xc7, 0x01]),
+        (2097151, &[0xff, 0xff, 0x7f]),
+        (2147483647, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+        (-1, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        (-2147483648, &[0x80, 0x80, 0x80, 0x80, 0x08]),
+    ];
+
+    const VAR_LONGS: &[(i64, &[u8])] = &[
+        (0, &[0x00]),
+        (1, &[0x01]),
+        (2, &[0x02]),
+        (127, &[0x7f]),
+        (128, &[0x80, 0x01]),
+        (255, &[0xff, 0x01]),
+        (2147483647, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+        (
+            9223372036854775807,
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+        ),
+        (
+            -1,
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+        ),
+        (
+            -2147483648,
+            &[0x80, 0x80, 0x80, 0x80, 0xf8, 0xff, 0xff, 0xff, 0xff, 0x01],
+        ),
+        (
+            -9223372036854775808,
+            &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01],
+        ),
+    ];
+
+    fn write_var<T: MinecraftWriteableVar<Cursor<Vec<u8>>>>(value: T) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        block_on(value.write_var_to(&mut bytes)).unwrap();
+        bytes.into_inner()
+    }
+
+    fn read_var<T: MinecraftReadableVar<Cursor<Vec<u8>>>>(bytes: &[u8]) -> T {
+        let mut bytes = Cursor::new(bytes.to_vec());
+        let value = block_on(T::read_var_from(&mut bytes)).unwrap();
+        assert_eq!(bytes.position() as usize, bytes.get_ref().len());
+        value
+    }
+
+    #[test]
+    fn var_ints_match_the_wire_format() {
+        for &(value, bytes) in VAR_INTS {
+            assert_eq!(write_var(value), bytes, "writing {}", value);
+            assert_eq!(read_var::<i32>(bytes), value);
+        }
+    }
+
+    #[test]
+    fn var_longs_match_the_wire_format() {
+        for &(value, bytes) in VAR_LONGS {
+            assert_eq!(write_var(value), bytes, "writing {}", value);
+            assert_eq!(read_var::<i64>(bytes), value);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn var_ints_round_trip(value: i32) {
+            let bytes = write_var(value);
+            prop_assert!(bytes.len() <= 5);
+            prop_assert_eq!(read_var::<i32>(&bytes), value);
+        }
+
+        #[test]
+        fn var_longs_round_trip(value: i64) {
+            let bytes = write_var(value);
+            prop_assert!(bytes.len() <= 10);
+            prop_assert_eq!(read_var::<i64>(&bytes), value);
+        }
+    }
+}
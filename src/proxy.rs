@@ -0,0 +1,794 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    net::IpAddr,
+    sync::{atomic::AtomicU64, Arc},
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use async_std::{
+    future::timeout,
+    io::{copy, Read, Write, WriteExt},
+    net::TcpStream,
+};
+use colored::Colorize;
+use futures::{future::join_all, try_join};
+use serde_json::Map;
+
+use crate::{
+    backend::{fetch_status, BackendAddress},
+    cache::{CacheKey, StatusCache},
+    config::{Config, MaxPlayers},
+    health::{Health, HealthMonitor},
+    limit::RateLimiter,
+    maintenance::Maintenance,
+    motd::legacy_text,
+    protocol::{
+        legacy::{read_legacy_ping, LegacyPingResponse, LEGACY_PING},
+        packets::{
+            HandshakePacket, LoginDisconnectPacket, LoginStartPacket, NextState, PingRequestPacket,
+            PongResponsePacket, StatusResponsePacket,
+        },
+        read::MinecraftReadable,
+        stream::MinecraftStream,
+    },
+    route::Route,
+    stats::Stats,
+    status::{build_sample, favicon, text_component, Players, StatusResponse, Version},
+    template::{fill, Placeholders},
+};
+
+/// Everything that is shared between connections, apart from the config.
+#[derive(Debug)]
+pub struct State {
+    pub cache: Arc<StatusCache>,
+    pub health: Arc<HealthMonitor>,
+    pub maintenance: Arc<Maintenance>,
+    pub limiter: Arc<RateLimiter>,
+    pub stats: Arc<Stats>,
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            cache: Arc::new(StatusCache::new()),
+            health: Arc::new(HealthMonitor::new()),
+            maintenance: Arc::new(Maintenance::new()),
+            limiter: Arc::new(RateLimiter::new()),
+            stats: Arc::new(Stats::new()),
+        }
+    }
+}
+
+/// A player that got through every check before logging in, ready to be sent to the backend.
+#[derive(Debug)]
+pub struct Login {
+    /// The handshake to send to the backend, with its address already rewritten.
+    pub handshake: HandshakePacket,
+    pub login_start: LoginStartPacket,
+    pub route: Route,
+    pub values: Placeholders,
+}
+
+/// Waits for `future`, giving up with an error if it takes longer than `duration`.
+///
+/// Timeouts are added to `counter`.
+async fn deadline<T>(
+    duration: Duration,
+    counter: &AtomicU64,
+    future: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    match timeout(duration, future).await {
+        Ok(result) => result,
+        Err(err) => {
+            Stats::count(counter);
+            Err(err.into())
+        }
+    }
+}
+
+/// Handles a connection from a client, connecting it to the backend once it has logged in.
+pub async fn handle_conn(
+    mut client: TcpStream,
+    config: Arc<Config>,
+    state: Arc<State>,
+) -> Result<(), Error> {
+    let accepted = Instant::now();
+    let ip = client.peer_addr()?.ip();
+
+    let mut first = [0u8];
+    let peeked = deadline(
+        config.timeouts.handshake(),
+        &state.stats.handshake_timeouts,
+        async { Ok(client.peek(&mut first).await?) },
+    )
+    .await?;
+    let legacy = peeked == 1 && first[0] == LEGACY_PING;
+
+    let Some(mut login) = pre_login(&mut client, ip, legacy, accepted, &config, &state).await?
+    else {
+        return Ok(());
+    };
+    let (route, name) = (&login.route, &login.login_start.name);
+
+    println!(
+        "{} {} {}",
+        "Connecting player".bright_yellow(),
+        name.blue().bold(),
+        "to server...".bright_yellow()
+    );
+    let server = if state.health.health(&route.target) == Health::Down {
+        Err(Error::msg("backend is down"))
+    } else {
+        timeout(
+            config.timeouts.backend_connect(),
+            TcpStream::connect((route.target.host.as_str(), route.target.port)),
+        )
+        .await
+        .map_err(Error::from)
+        .and_then(|server| Ok(server?))
+    };
+    let mut server = match server {
+        Ok(server) => server,
+        Err(err) => {
+            println!(
+                "{} {} {} {}",
+                "Couldn't connect".bright_yellow(),
+                name.blue().bold(),
+                "as the server is offline:".red(),
+                err
+            );
+
+            return disconnect(&mut client, &config.messages.backend_offline, &login.values).await;
+        }
+    };
+    server.write_packet(&mut login.handshake).await?;
+    server.write_packet(&mut login.login_start).await?;
+    Stats::count(&state.stats.logins);
+
+    let (mut client_recv, mut client_send) = (&client, &client);
+    let (mut server_recv, mut server_send) = (&server, &server);
+
+    let client_to_server = copy(&mut client_recv, &mut server_send);
+    let server_to_client = copy(&mut server_recv, &mut client_send);
+
+    let (_, _) = try_join!(client_to_server, server_to_client)?;
+
+    Ok(())
+}
+
+/// Handles everything a client sends before it is connected to the backend: server list pings,
+/// the handshake and the login start.
+///
+/// `legacy` is whether the client started with a pre 1.7 server list ping. `accepted` is when the
+/// connection was accepted, as the handshake timeout counts from then. If the player can log in,
+/// the login to send to the backend is returned, otherwise the client has been answered or kicked
+/// already.
+pub async fn pre_login<S: Read + Write + Unpin + Send + Sync + Debug>(
+    client: &mut S,
+    ip: IpAddr,
+    legacy: bool,
+    accepted: Instant,
+    config: &Arc<Config>,
+    state: &State,
+) -> Result<Option<Login>, Error> {
+    let limiter = &state.limiter;
+    let stats = &state.stats;
+
+    if legacy {
+        if !config.ip_lists.status.allows(ip) {
+            limiter.refused(ip);
+            return Ok(None);
+        }
+        if !limiter.status(ip, &config.rate_limits) {
+            limiter.log(ip, "for pinging too often.");
+            return Ok(None);
+        }
+        Stats::count(&stats.pings);
+        handle_legacy_ping(client, config, state).await?;
+        return Ok(None);
+    }
+
+    let mut handshake: HandshakePacket = deadline(
+        config
+            .timeouts
+            .handshake()
+            .saturating_sub(accepted.elapsed()),
+        &stats.handshake_timeouts,
+        client.read_packet_framed(&config.packet_limits.handshake()),
+    )
+    .await?;
+    let rules = match handshake.next_state {
+        NextState::Status => &config.ip_lists.status,
+        NextState::Login => &config.ip_lists.login,
+    };
+    if !rules.allows(ip) {
+        limiter.refused(ip);
+        return Ok(None);
+    }
+    let route = config.route(&handshake.server_address);
+
+    if handshake.next_state == NextState::Status {
+        if !limiter.status(ip, &config.rate_limits) {
+            limiter.log(ip, "for pinging too often.");
+            return Ok(None);
+        }
+        Stats::count(&stats.pings);
+        for _ in 0..2 {
+            let (id, mut frame) = deadline(
+                config.timeouts.status(),
+                &stats.status_timeouts,
+                client.read_frame(&config.packet_limits.status()),
+            )
+            .await?;
+            if id == 0 {
+                // get the real player count and check if online
+                let probe = HandshakePacket {
+                    protocol_version: handshake.protocol_version,
+                    server_address: route
+                        .rewrite_host
+                        .clone()
+                        .unwrap_or_else(|| handshake.server_address.clone()),
+                    server_port: handshake.server_port,
+                    next_state: NextState::Status,
+                };
+                let response = status_response(config, &route, state, probe).await;
+                client
+                    .write_packet(&mut StatusResponsePacket {
+                        response: response.to_json()?,
+                    })
+                    .await?;
+            } else if id == 1 {
+                let request = PingRequestPacket::read_from(&mut frame).await?;
+
+                client
+                    .write_packet(&mut PongResponsePacket {
+                        payload: request.payload,
+                    })
+                    .await?;
+            }
+        }
+        return Ok(None);
+    }
+
+    handshake.server_address = route
+        .rewrite_host
+        .clone()
+        .unwrap_or_else(|| route.target.host.clone());
+    handshake.server_port = route.target.port;
+
+    let login_start: LoginStartPacket = deadline(
+        config.timeouts.login_start(),
+        &stats.login_timeouts,
+        client.read_packet_framed(&config.packet_limits.login()),
+    )
+    .await?;
+
+    let values = Placeholders {
+        player: Some(login_start.name.clone()),
+        backend: Some(route.target.to_string()),
+        ..Placeholders::default()
+    };
+    if !limiter.login(ip, &config.rate_limits) {
+        limiter.log(
+            ip,
+            &format!("for logging in too often, as {}.", login_start.name),
+        );
+        disconnect(client, &config.rate_limits.message, &values).await?;
+        return Ok(None);
+    }
+    if !config.player_lists.allows(&login_start.name) {
+        println!(
+            "{} {} {}",
+            "Kicked".bright_yellow(),
+            login_start.name.blue().bold(),
+            "as they aren't allowed to join.".yellow()
+        );
+        disconnect(client, &config.player_lists.message, &values).await?;
+        return Ok(None);
+    }
+    if state.maintenance.is_enabled(config) && !config.maintenance.allows(&login_start.name) {
+        println!(
+            "{} {} {}",
+            "Kicked".bright_yellow(),
+            login_start.name.blue().bold(),
+            "as the server is in maintenance.".yellow()
+        );
+        disconnect(client, &config.maintenance.message, &values).await?;
+        return Ok(None);
+    }
+
+    Ok(Some(Login {
+        handshake,
+        login_start,
+        route,
+        values,
+    }))
+}
+
+/// Answers a server list ping from a client older than 1.7.
+async fn handle_legacy_ping<S: Read + Write + Unpin + Send + Sync>(
+    client: &mut S,
+    config: &Config,
+    state: &State,
+) -> Result<(), Error> {
+    let ping = deadline(
+        config.timeouts.handshake(),
+        &state.stats.handshake_timeouts,
+        read_legacy_ping(client),
+    )
+    .await?;
+    let route = match &ping.server_address {
+        Some(address) => config.route(address),
+        None => config.default_route(),
+    };
+
+    let probe = HandshakePacket {
+        protocol_version: ping.protocol_version.unwrap_or(-1),
+        server_address: route
+            .rewrite_host
+            .clone()
+            .or(ping.server_address)
+            .unwrap_or_else(|| route.target.host.clone()),
+        server_port: ping.server_port.unwrap_or(route.target.port),
+        next_state: NextState::Status,
+    };
+    let response = status_response(config, &route, state, probe).await;
+    let players = response.players.unwrap_or(Players {
+        max: 0,
+        online: 0,
+        sample: None,
+    });
+
+    let response = LegacyPingResponse {
+        protocol_version: response.version.protocol,
+        version: response.version.name,
+        motd: legacy_text(&response.description),
+        online: players.online,
+        max: players.max,
+    };
+    client.write_all(&response.to_bytes()).await?;
+    client.flush().await?;
+
+    Ok(())
+}
+
+/// Builds the reply to a status request, asking the backend for its version and players.
+///
+/// If the backend can't be reached, the offline MOTD is shown instead.
+async fn status_response(
+    config: &Config,
+    route: &Route,
+    state: &State,
+    probe: HandshakePacket,
+) -> StatusResponse {
+    let (cache, health) = (&state.cache, &state.health);
+    let protocol_version = probe.protocol_version;
+    if state.maintenance.is_enabled(config) {
+        println!(
+            "{} {}{}",
+            "Received status request, responding with".blue(),
+            "maintenance".yellow().bold(),
+            ".".blue()
+        );
+
+        let values = Placeholders {
+            online: Some(0),
+            max: Some(0),
+            backend: Some(route.target.to_string()),
+            version: Some(config.maintenance.version.clone()),
+            player: None,
+        };
+        let mut description = text_component(&config.maintenance.motd);
+        fill(&mut description, &values);
+
+        // -1 never matches the client's version, so the version name is shown
+        return StatusResponse {
+            version: Version {
+                name: config.maintenance.version.clone(),
+                protocol: -1,
+            },
+            players: Some(Players {
+                max: 0,
+                online: 0,
+                sample: Some(build_sample(None, &config.sample, &values).unwrap_or_default()),
+            }),
+            description,
+            favicon: favicon(&route.favicon),
+            enforces_secure_chat: Some(true),
+            previews_chat: Some(true),
+            extra: Map::new(),
+        };
+    }
+    let status = backend_status(config, cache, health, &route.target, probe.clone()).await;
+
+    if let Ok(mut status) = status {
+        println!(
+            "{} {}{}",
+            "Received status request, responded with ".bright_blue(),
+            "online".green().bold(),
+            ".".bright_blue()
+        );
+
+        let mut version = status.version;
+        if let Some(name) = &config.version.name {
+            version.name = name.clone();
+        }
+        if let Some(players) = &mut status.players {
+            count_players(players, config, cache, health, &probe).await;
+        }
+
+        let values = Placeholders {
+            online: status.players.as_ref().map(|players| players.online),
+            max: status.players.as_ref().map(|players| players.max),
+            backend: Some(route.target.to_string()),
+            version: Some(version.name.clone()),
+            player: None,
+        };
+        let mut description = text_component(route.motd.pick());
+        fill(&mut description, &values);
+
+        let players = status.players.map(|players| Players {
+            sample: build_sample(players.sample, &config.sample, &values),
+            ..players
+        });
+
+        StatusResponse {
+            version,
+            players,
+            description,
+            favicon: favicon(&route.favicon),
+            enforces_secure_chat: status.enforces_secure_chat,
+            previews_chat: status.previews_chat,
+            // keep `forgeData` and `modinfo`, so modded clients can see which mods are needed
+            extra: status.extra,
+        }
+    } else {
+        println!(
+            "{} {}{}",
+            "Received status request, responding with".blue(),
+            "offline".red().bold(),
+            ".".blue()
+        );
+
+        let values = Placeholders {
+            online: Some(0),
+            max: Some(0),
+            backend: Some(route.target.to_string()),
+            version: Some(config.version.offline_name.clone()),
+            player: None,
+        };
+        let mut description = text_component(route.offline_motd.pick());
+        fill(&mut description, &values);
+
+        StatusResponse {
+            version: Version {
+                name: config.version.offline_name.clone(),
+                protocol: config.version.offline_protocol.unwrap_or(protocol_version),
+            },
+            players: Some(Players {
+                max: 0,
+                online: 0,
+                sample: Some(build_sample(None, &config.sample, &values).unwrap_or_default()),
+            }),
+            description,
+            favicon: favicon(&route.favicon),
+            enforces_secure_chat: Some(true),
+            previews_chat: Some(true),
+            extra: Map::new(),
+        }
+    }
+}
+
+/// Gets a backend's status from the cache, or asks the backend for it.
+async fn backend_status(
+    config: &Config,
+    cache: &Arc<StatusCache>,
+    health: &HealthMonitor,
+    backend: &BackendAddress,
+    probe: HandshakePacket,
+) -> Result<StatusResponse, Error> {
+    if health.health(backend) == Health::Down {
+        return Err(Error::msg("backend is down"));
+    }
+
+    let key = CacheKey {
+        backend: backend.clone(),
+        host: probe.server_address.clone(),
+        protocol_version: Some(probe.protocol_version),
+    };
+    let target = backend.clone();
+    let (connect_timeout, read_timeout, framing) = (
+        config.timeouts.backend_connect(),
+        config.timeouts.backend_read(),
+        config.packet_limits.backend_status(),
+    );
+    cache
+        .get(
+            key,
+            config.status_cache.ttl(),
+            config.status_cache.stale(),
+            || async move {
+                fetch_status(&target, probe, connect_timeout, read_timeout, framing).await
+            },
+        )
+        .await
+}
+
+/// Applies the `[players]` overrides to the player counts from the backend.
+async fn count_players(
+    players: &mut Players,
+    config: &Config,
+    cache: &Arc<StatusCache>,
+    health: &HealthMonitor,
+    probe: &HandshakePacket,
+) {
+    let settings = &config.players;
+    if !settings.online_from.is_empty() {
+        let statuses = join_all(
+            settings
+                .online_backends
+                .iter()
+                .map(|backend| backend_status(config, cache, health, backend, probe.clone())),
+        )
+        .await;
+        // backends that are offline don't have anyone online
+        players.online = statuses
+            .into_iter()
+            .filter_map(|status| status.ok()?.players)
+            .map(|players| players.online)
+            .sum();
+    }
+
+    match settings.max_mode {
+        MaxPlayers::Backend => {}
+        MaxPlayers::Fixed => players.max = settings.max,
+        MaxPlayers::OnlinePlusOne => players.max = players.online + 1,
+    }
+}
+
+/// Kicks a player during login, with `message` as the reason.
+async fn disconnect<S: Read + Write + Unpin + Send + Sync + Debug>(
+    client: &mut S,
+    message: &str,
+    values: &Placeholders,
+) -> Result<(), Error> {
+    let mut reason = text_component(message);
+    fill(&mut reason, values);
+    client
+        .write_packet(&mut LoginDisconnectPacket {
+            reason: reason.to_string(),
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv4Addr, sync::atomic::Ordering};
+
+    use async_std::{
+        net::{TcpListener, TcpStream},
+        task::{block_on, spawn},
+    };
+
+    use super::*;
+    use crate::protocol::{packets::StatusRequestPacket, stream::MemoryStream};
+
+    fn config(maintenance: bool) -> Arc<Config> {
+        let mut config = Config {
+            target_ip: "127.0.0.1:1".to_owned(),
+            ..Config::default()
+        };
+        config.maintenance.enabled = maintenance;
+        config.validate().unwrap();
+        Arc::new(config)
+    }
+
+    fn run(input: Vec<u8>, maintenance: bool) -> (Result<Option<Login>, Error>, Vec<u8>) {
+        let legacy = input.first() == Some(&LEGACY_PING);
+        let mut client = MemoryStream::new(input);
+        let result = block_on(pre_login(
+            &mut client,
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            legacy,
+            Instant::now(),
+            &config(maintenance),
+            &State::new(),
+        ));
+        (result, client.output)
+    }
+
+    fn login_packets(next_state: NextState) -> Vec<u8> {
+        let mut client = MemoryStream::new(vec![]);
+        block_on(async {
+            client
+                .write_packet(&mut HandshakePacket {
+                    protocol_version: 765,
+                    server_address: "play.example.net".to_owned(),
+                    server_port: 25565,
+                    next_state,
+                })
+                .await?;
+            client
+                .write_packet(&mut LoginStartPacket {
+                    name: "Steve".to_owned(),
+                    uuid: 0,
+                })
+                .await
+        })
+        .unwrap();
+        client.output
+    }
+
+    #[test]
+    fn returns_logins_to_forward() {
+        let (login, output) = run(login_packets(NextState::Login), false);
+        let login = login.unwrap().unwrap();
+        assert_eq!(login.login_start.name, "Steve");
+        assert_eq!(login.handshake.server_address, "127.0.0.1");
+        assert_eq!(login.handshake.server_port, 1);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn kicks_logins_in_maintenance() {
+        let (login, output) = run(login_packets(NextState::Login), true);
+        assert!(login.unwrap().is_none());
+        // a login disconnect packet
+        assert_eq!(output[1], 0);
+    }
+
+    #[test]
+    fn lets_allowlisted_players_join_in_maintenance() {
+        let mut config = Config {
+            target_ip: "127.0.0.1:1".to_owned(),
+            ..Config::default()
+        };
+        config.maintenance.enabled = true;
+        config.maintenance.allowlist = vec!["steve".to_owned()];
+        config.validate().unwrap();
+        let mut client = MemoryStream::new(login_packets(NextState::Login));
+        let login = block_on(pre_login(
+            &mut client,
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            false,
+            Instant::now(),
+            &Arc::new(config),
+            &State::new(),
+        ));
+        assert_eq!(login.unwrap().unwrap().login_start.name, "Steve");
+    }
+
+    #[test]
+    fn times_out_handshakes_from_when_the_connection_was_accepted() {
+        let state = State::new();
+        let result = block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let _silent = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let (mut client, _) = listener.accept().await.unwrap();
+            // the whole handshake timeout was used up before the handshake was read
+            let accepted = Instant::now() - config(false).timeouts.handshake();
+            pre_login(
+                &mut client,
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                false,
+                accepted,
+                &config(false),
+                &state,
+            )
+            .await
+        });
+        assert!(result.is_err());
+        assert_eq!(state.stats.handshake_timeouts.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn answers_legacy_pings() {
+        let (login, output) = run(vec![LEGACY_PING, 0x01], true);
+        assert!(login.unwrap().is_none());
+        assert_eq!(output[0], 0xFF);
+    }
+
+    /// Malformed input, including lengths that used to panic or allocate far too much.
+    #[test]
+    fn rejects_malformed_input() {
+        let inputs: &[&[u8]] = &[
+            &[],
+            &[LEGACY_PING],
+            &[LEGACY_PING, 0x01, 0xFA, 0xFF, 0xFF],
+            // negative packet length
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x00],
+            // a 256MiB packet
+            &[0x80, 0x80, 0x80, 0x80, 0x01],
+            // a handshake with a negative address length
+            &[0x07, 0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F],
+            // a handshake with an unknown next state
+            &[0x06, 0x00, 0x01, 0x00, 0x63, 0xDD, 0x03],
+            // a VarInt that never ends
+            &[0x80; 16],
+        ];
+        for input in inputs {
+            for maintenance in [false, true] {
+                let (result, _) = run(input.to_vec(), maintenance);
+                assert!(
+                    !matches!(result, Ok(Some(_))),
+                    "{:?} should not log in",
+                    input
+                );
+            }
+        }
+        // a login start where a status request should be
+        let (result, _) = run(login_packets(NextState::Status), true);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn keeps_backend_fields_in_replies() {
+        const FORGE: &str = r#"{"version":{"name":"1.20.1","protocol":763},"players":{"max":20,"online":0},"description":"A Forge Server","enforcesSecureChat":false,"forgeData":{"channels":[],"mods":[],"truncated":false,"fmlNetworkVersion":3,"d":""}}"#;
+
+        let output = block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let mut config = Config {
+                target_ip: backend.local_addr().unwrap().to_string(),
+                ..Config::default()
+            };
+            config.validate().unwrap();
+            spawn(async move {
+                let (mut server, _) = backend.accept().await.unwrap();
+                let _: HandshakePacket = server.read_packet().await.unwrap();
+                let _: StatusRequestPacket = server.read_packet().await.unwrap();
+                server
+                    .write_packet(&mut StatusResponsePacket {
+                        response: FORGE.to_owned(),
+                    })
+                    .await
+                    .unwrap();
+            });
+
+            let mut client = MemoryStream::new(vec![]);
+            client
+                .write_packet(&mut HandshakePacket {
+                    protocol_version: 763,
+                    server_address: "localhost".to_owned(),
+                    server_port: 25565,
+                    next_state: NextState::Status,
+                })
+                .await
+                .unwrap();
+            client
+                .write_packet(&mut StatusRequestPacket {})
+                .await
+                .unwrap();
+            let mut client = MemoryStream::new(client.output);
+            let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+            // the client doesn't ping after the status request, so this ends with an error
+            let _ = pre_login(
+                &mut client,
+                ip,
+                false,
+                Instant::now(),
+                &Arc::new(config),
+                &State::new(),
+            )
+            .await;
+            client.output
+        });
+
+        let reply: StatusResponsePacket =
+            block_on(MemoryStream::new(output).read_packet()).unwrap();
+        let reply = StatusResponse::parse(&reply.response).unwrap();
+        assert_eq!(reply.extra["forgeData"]["fmlNetworkVersion"], 3);
+        assert_eq!(reply.enforces_secure_chat, Some(false));
+        assert_eq!(reply.previews_chat, None);
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use async_std::task::sleep;
+use colored::Colorize;
+
+use crate::{
+    config::{Config, SharedConfig},
+    motd::print_motd,
+};
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// When the config file and the files it loads were last changed.
+fn all_modified(path: &PathBuf, config: &Config) -> Vec<Option<SystemTime>> {
+    let mut times = vec![modified(path)];
+    times.extend(config.list_files().iter().map(modified));
+    times
+}
+
+/// Watches the config file, and the list files it loads, and swaps in the new config whenever
+/// they change.
+///
+/// If the new config can't be loaded, the error is logged and the old config is kept.
+pub async fn watch_config(path: PathBuf, args: Vec<String>, shared: SharedConfig) {
+    let mut last_modified = all_modified(&path, &shared.get());
+
+    loop {
+        let interval = shared.get().reload_interval;
+        if interval == 0 {
+            return;
+        }
+        sleep(Duration::from_millis(interval)).await;
+
+        let current = all_modified(&path, &shared.get());
+        if current == last_modified {
+            continue;
+        }
+        last_modified = current;
+
+        match Config::load_with_args(&path, &args) {
+            Ok(config) => {
+                let old = shared.get();
+                if config.listening_ip != old.listening_ip {
+                    println!(
+                        "{} {}",
+                        "Changing listening_ip needs a restart, still listening on".yellow(),
+                        old.listening_ip.green().bold()
+                    );
+                }
+                println!("{}", "Reloaded config.".bright_green().bold());
+                println!("====================");
+                print_motd(&config);
+                println!("====================");
+                // the list files might have changed too
+                last_modified = all_modified(&path, &config);
+                shared.set(config);
+            }
+            Err(err) => {
+                println!(
+                    "{} {}",
+                    "Couldn't reload config, keeping the old one:".red().bold(),
+                    err
+                );
+            }
+        }
+    }
+}
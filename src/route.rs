@@ -0,0 +1,204 @@
+use serde::Deserialize;
+
+use crate::{backend::BackendAddress, config::Config, motd::MotdPool};
+
+/// Sends players that connect with one of `hosts` to a different backend.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    /// The hostnames this route is used for.
+    ///
+    /// These can either be exact, e.g. `survival.example.net`, or a wildcard
+    /// like `*.play.example.net` which matches any subdomain.
+    pub hosts: Vec<String>,
+    pub target_ip: String,
+    #[serde(default = "default_port")]
+    pub target_port: u16,
+    #[serde(skip)]
+    pub target: BackendAddress,
+    /// Falls back to the top level `motd` if it isn't set.
+    pub motd: Option<String>,
+    /// Falls back to the route's `motd` with "(Offline)" added if it isn't set.
+    pub offline_motd: Option<String>,
+    /// A list of MOTDs to use instead of `motd`.
+    pub motds: Option<MotdPool>,
+    /// A list of MOTDs to use instead of `offline_motd`.
+    pub offline_motds: Option<MotdPool>,
+    /// The MOTDs that are actually shown, after falling back. This is filled in by
+    /// [`Config::validate`].
+    #[serde(skip)]
+    pub motd_pool: MotdPool,
+    #[serde(skip)]
+    pub offline_motd_pool: MotdPool,
+    /// Falls back to the top level `favicon` if it isn't set.
+    pub favicon: Option<String>,
+    /// Falls back to the top level `rewrite_host` if it isn't set.
+    pub rewrite_host: Option<String>,
+}
+
+fn default_port() -> u16 {
+    25565
+}
+
+/// Where a connection gets sent and what it is shown, after picking a route.
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub target: BackendAddress,
+    pub motd: MotdPool,
+    pub offline_motd: MotdPool,
+    pub favicon: String,
+    pub rewrite_host: Option<String>,
+}
+
+/// Cleans up the address sent in the handshake so it can be matched against.
+///
+/// Forge appends `\0FML\0` to the address, and some clients keep the trailing dot from DNS.
+pub fn normalize_host(address: &str) -> String {
+    let host = address.split('\0').next().unwrap_or_default();
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn matches_wildcard(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix('*') {
+        Some(suffix) => host.len() > suffix.len() && host.ends_with(&suffix.to_ascii_lowercase()),
+        None => false,
+    }
+}
+
+impl Config {
+    /// Finds the route for the address the client connected with.
+    ///
+    /// Exact hosts are checked first, then wildcards from the most to the least specific.
+    /// If nothing matches, the top level settings are used.
+    pub fn route(&self, address: &str) -> Route {
+        let host = normalize_host(address);
+
+        let exact = self.routes.iter().find(|route| {
+            route
+                .hosts
+                .iter()
+                .any(|pattern| pattern.eq_ignore_ascii_case(&host))
+        });
+        let route = exact.or_else(|| {
+            self.routes
+                .iter()
+                .filter_map(|route| {
+                    route
+                        .hosts
+                        .iter()
+                        .filter(|pattern| matches_wildcard(pattern, &host))
+                        .map(|pattern| pattern.len())
+                        .max()
+                        .map(|len| (len, route))
+                })
+                .max_by_key(|(len, _)| *len)
+                .map(|(_, route)| route)
+        });
+
+        match route {
+            Some(route) => Route {
+                target: route.target.clone(),
+                motd: route.motd_pool.clone(),
+                offline_motd: route.offline_motd_pool.clone(),
+                favicon: route
+                    .favicon
+                    .clone()
+                    .unwrap_or_else(|| self.favicon.clone()),
+                rewrite_host: route
+                    .rewrite_host
+                    .clone()
+                    .or_else(|| self.rewrite_host.clone()),
+            },
+            None => self.default_route(),
+        }
+    }
+
+    /// The route used for hosts that don't match any of the configured routes.
+    pub fn default_route(&self) -> Route {
+        Route {
+            target: self.target.clone(),
+            motd: self.motd_pool.clone(),
+            offline_motd: self.offline_motd_pool.clone(),
+            favicon: self.favicon.clone(),
+            rewrite_host: self.rewrite_host.clone(),
+        }
+    }
+}
+
+/// Builds an offline MOTD by adding "(Offline)" to the end of `motd`.
+pub fn offline_motd_from(motd: &str) -> String {
+    let mut chars = motd.chars();
+    if chars.next() == Some('[') && chars.next_back() == Some(']') {
+        chars = motd.chars();
+        chars.next_back();
+        chars.as_str().to_owned()
+            + r#",{"text":" (","color":"gray"},{"text":"Offline","color":"red"},{"text":")","color":"gray"}]"#
+    } else {
+        motd.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        let mut config: Config = toml::from_str(
+            r#"
+            target_ip = "10.0.0.1"
+
+            [[routes]]
+            hosts = ["survival.example.net"]
+            target_ip = "10.0.0.2"
+
+            [[routes]]
+            hosts = ["*.example.net"]
+            target_ip = "10.0.0.3"
+
+            [[routes]]
+            hosts = ["*.play.example.net", "Lobby.Example.NET"]
+            target_ip = "10.0.0.4"
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        config
+    }
+
+    #[test]
+    fn picks_routes() {
+        let config = config();
+        for (address, target) in [
+            // exact hosts win over wildcards
+            ("survival.example.net", "10.0.0.2"),
+            ("creative.example.net", "10.0.0.3"),
+            // the longest wildcard wins
+            ("a.play.example.net", "10.0.0.4"),
+            ("a.b.play.example.net", "10.0.0.4"),
+            // hosts aren't case sensitive
+            ("SURVIVAL.Example.Net", "10.0.0.2"),
+            ("lobby.example.net", "10.0.0.4"),
+            ("A.PLAY.example.net", "10.0.0.4"),
+            // forge's suffix and the trailing dot from DNS are ignored
+            ("survival.example.net\0FML\0", "10.0.0.2"),
+            ("survival.example.net\0FML3\0", "10.0.0.2"),
+            ("survival.example.net.", "10.0.0.2"),
+            // wildcards need a subdomain
+            ("example.net", "10.0.0.1"),
+            ("play.example.net", "10.0.0.3"),
+            ("example.org", "10.0.0.1"),
+            ("", "10.0.0.1"),
+        ] {
+            assert_eq!(config.route(address).target.host, target, "{:?}", address);
+        }
+    }
+
+    #[test]
+    fn normalizes_hosts() {
+        assert_eq!(
+            normalize_host("Play.Example.NET.\0FML\0"),
+            "play.example.net"
+        );
+        assert_eq!(normalize_host("play.example.net"), "play.example.net");
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use colored::Colorize;
+
+/// Counters for what the proxy has done since it started.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub connections: AtomicU64,
+    pub pings: AtomicU64,
+    pub logins: AtomicU64,
+    /// Connections closed for taking too long to send their handshake.
+    pub handshake_timeouts: AtomicU64,
+    /// Connections closed for taking too long to send a status request or ping.
+    pub status_timeouts: AtomicU64,
+    /// Connections closed for taking too long to send their login start.
+    pub login_timeouts: AtomicU64,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn count(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn print(&self) {
+        for (name, counter) in [
+            ("Connections", &self.connections),
+            ("Pings", &self.pings),
+            ("Logins", &self.logins),
+            ("Handshake timeouts", &self.handshake_timeouts),
+            ("Status timeouts", &self.status_timeouts),
+            ("Login timeouts", &self.login_timeouts),
+        ] {
+            println!(
+                "{} {}",
+                format!("{}:", name).bright_blue(),
+                counter.load(Ordering::Relaxed).to_string().bold()
+            );
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    config::{SampleConfig, SampleMode},
+    template::{fill_text, Placeholders},
+};
+
+/// The id sent for sample entries that aren't real players.
+const NIL_UUID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// The JSON sent in a [`StatusResponsePacket`](crate::protocol::packets::StatusResponsePacket).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusResponse {
+    pub version: Version,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub players: Option<Players>,
+    /// The MOTD, as a JSON text component.
+    #[serde(default)]
+    pub description: Value,
+    /// A base64 PNG data URI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enforces_secure_chat: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previews_chat: Option<bool>,
+    /// Anything else the server sent, like `forgeData` or `modinfo`, so it isn't lost.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Version {
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Players {
+    pub max: i32,
+    pub online: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<Vec<PlayerSample>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSample {
+    pub name: String,
+    pub id: String,
+}
+
+impl StatusResponse {
+    pub fn parse(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+/// Parses a MOTD from the config into a text component.
+///
+/// MOTDs are checked when the config is loaded, but anything that isn't JSON is shown as plain text.
+pub fn text_component(json: &str) -> Value {
+    serde_json::from_str(json).unwrap_or_else(|_| Value::String(json.to_owned()))
+}
+
+/// Builds the player sample shown when hovering over the player count.
+///
+/// `real` is the sample the backend sent, if any.
+pub fn build_sample(
+    real: Option<Vec<PlayerSample>>,
+    config: &SampleConfig,
+    values: &Placeholders,
+) -> Option<Vec<PlayerSample>> {
+    let real = real.map(|sample| {
+        if config.hide_names {
+            sample
+                .into_iter()
+                .map(|_| PlayerSample {
+                    name: "Anonymous Player".to_owned(),
+                    id: NIL_UUID.to_owned(),
+                })
+                .collect()
+        } else {
+            sample
+        }
+    });
+    if config.entries.is_empty() && config.mode == SampleMode::Append {
+        return real;
+    }
+
+    let mut sample = match config.mode {
+        SampleMode::Append => real.unwrap_or_default(),
+        SampleMode::Replace => vec![],
+    };
+    sample.extend(config.entries.iter().map(|entry| PlayerSample {
+        name: fill_text(entry, values),
+        id: NIL_UUID.to_owned(),
+    }));
+    Some(sample)
+}
+
+/// Turns an empty favicon from the config into `None`.
+pub fn favicon(favicon: &str) -> Option<String> {
+    if favicon.is_empty() {
+        None
+    } else {
+        Some(favicon.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VANILLA: &str = r#"{"version":{"name":"1.20.4","protocol":765},"enforcesSecureChat":true,"description":{"text":"A Minecraft Server"},"players":{"max":20,"online":2,"sample":[{"id":"4566e69f-c907-48ee-8d71-d7ba5aa00d20","name":"thinkofdeath"},{"id":"069a79f4-44e9-4726-a5be-fca90e38aaf5","name":"Notch"}]}}"#;
+
+    const PAPER: &str = r#"{"description":{"extra":[{"color":"gold","text":"Survival "},{"bold":true,"color":"green","text":"Season 4"}],"text":""},"players":{"max":100,"online":0},"version":{"name":"Paper 1.20.4","protocol":765},"favicon":"data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAEAAAABACAYAAACqaXHeAAAAAXNSR0IArs4c6QAAAARnQU1BAACxjwv8YQUAAAAJcEhZcwAADsMAAA7DAcdvqGQAAAA","enforcesSecureChat":true,"previewsChat":false}"#;
+
+    const FORGE: &str = r#"{"description":{"text":"A Forge Server"},"players":{"max":20,"online":0},"version":{"name":"1.20.1","protocol":763},"forgeData":{"channels":[],"mods":[],"truncated":false,"fmlNetworkVersion":3,"d":"\u0000\u0000"},"enforcesSecureChat":false}"#;
+
+    // 1.8 servers send a plain string description and no secure chat fields
+    const LEGACY_STRING: &str = r#"{"version":{"name":"Spigot 1.8.8","protocol":47},"players":{"max":50,"online":1,"sample":[{"name":"jeb_","id":"853c80ef-3c37-49fd-aa49-938b674adae6"}]},"description":"§aA Spigot Server","modinfo":{"type":"FML","modList":[]}}"#;
+
+    const VELOCITY: &str = r#"{"version":{"name":"Velocity 3.3.0-SNAPSHOT","protocol":767},"players":{"max":500,"online":37,"sample":[]},"description":{"text":"A Velocity Server","color":"aqua"}}"#;
+
+    fn round_trip(json: &str) -> StatusResponse {
+        let status = StatusResponse::parse(json).unwrap();
+        let written = status.to_json().unwrap();
+
+        assert_eq!(StatusResponse::parse(&written).unwrap(), status);
+        assert_eq!(
+            serde_json::from_str::<Value>(&written).unwrap(),
+            serde_json::from_str::<Value>(json).unwrap()
+        );
+
+        status
+    }
+
+    #[test]
+    fn vanilla() {
+        let status = round_trip(VANILLA);
+        assert_eq!(status.version.protocol, 765);
+        let players = status.players.unwrap();
+        assert_eq!(players.online, 2);
+        assert_eq!(players.sample.unwrap()[1].name, "Notch");
+        assert_eq!(status.enforces_secure_chat, Some(true));
+    }
+
+    #[test]
+    fn paper_with_nested_description() {
+        let status = round_trip(PAPER);
+        assert_eq!(status.players.unwrap().sample, None);
+        assert_eq!(status.description["extra"][1]["text"], "Season 4");
+        assert!(status
+            .favicon
+            .unwrap()
+            .starts_with("data:image/png;base64,"));
+        assert_eq!(status.previews_chat, Some(false));
+    }
+
+    #[test]
+    fn forge_data_is_kept() {
+        let status = round_trip(FORGE);
+        assert_eq!(status.extra["forgeData"]["fmlNetworkVersion"], 3);
+    }
+
+    #[test]
+    fn legacy_string_description() {
+        let status = round_trip(LEGACY_STRING);
+        assert_eq!(
+            status.description,
+            Value::String("§aA Spigot Server".to_owned())
+        );
+        assert_eq!(status.enforces_secure_chat, None);
+        assert!(status.extra.contains_key("modinfo"));
+    }
+
+    #[test]
+    fn empty_sample() {
+        let status = round_trip(VELOCITY);
+        assert_eq!(status.players.unwrap().sample, Some(vec![]));
+    }
+
+    #[test]
+    fn text_component_falls_back_to_plain_text() {
+        assert_eq!(text_component(r#"{"text":"hi"}"#)["text"], "hi");
+        assert_eq!(
+            text_component("not json"),
+            Value::String("not json".to_owned())
+        );
+    }
+}
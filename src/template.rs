@@ -0,0 +1,153 @@
+use chrono::Local;
+use serde_json::Value;
+
+/// Every placeholder that can be used in MOTDs and messages.
+pub const PLACEHOLDERS: &[&str] = &["online", "max", "backend", "version", "player", "time"];
+
+/// The values to fill a template in with.
+///
+/// Placeholders that don't have a value, like `{player}` in a MOTD, are replaced with nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Placeholders {
+    pub online: Option<i32>,
+    pub max: Option<i32>,
+    pub backend: Option<String>,
+    pub version: Option<String>,
+    pub player: Option<String>,
+}
+
+impl Placeholders {
+    fn get(&self, name: &str) -> String {
+        match name {
+            "online" => self.online.map(|online| online.to_string()),
+            "max" => self.max.map(|max| max.to_string()),
+            "backend" => self.backend.clone(),
+            "version" => self.version.clone(),
+            "player" => self.player.clone(),
+            "time" => Some(Local::now().format("%H:%M").to_string()),
+            _ => None,
+        }
+        .unwrap_or_default()
+    }
+}
+
+/// Finds the `{name}` placeholders in a piece of text, along with where they are.
+fn placeholders(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.match_indices('{').filter_map(|(start, _)| {
+        let rest = &text[start + 1..];
+        let end = rest.find('}')?;
+        let name = &rest[..end];
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        valid.then_some((start, name))
+    })
+}
+
+/// Finds the first placeholder in a text component that isn't in [`PLACEHOLDERS`].
+pub fn unknown_placeholder(component: &Value) -> Option<String> {
+    match component {
+        Value::String(text) => placeholders(text)
+            .find(|(_, name)| !PLACEHOLDERS.contains(name))
+            .map(|(_, name)| name.to_owned()),
+        Value::Array(values) => values.iter().find_map(unknown_placeholder),
+        Value::Object(map) => map.values().find_map(unknown_placeholder),
+        _ => None,
+    }
+}
+
+/// Replaces the placeholders in every string of a text component.
+///
+/// This works on the parsed component, so values like player names can't break the JSON.
+pub fn fill(component: &mut Value, values: &Placeholders) {
+    match component {
+        Value::String(text) => *text = fill_text(text, values),
+        Value::Array(components) => {
+            for component in components {
+                fill(component, values);
+            }
+        }
+        Value::Object(map) => {
+            for component in map.values_mut() {
+                fill(component, values);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces the placeholders in a piece of plain text.
+pub fn fill_text(text: &str, values: &Placeholders) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, name) in placeholders(text) {
+        // placeholders are checked when the config is loaded, so leave anything else alone
+        if start < last || !PLACEHOLDERS.contains(&name) {
+            continue;
+        }
+        filled.push_str(&text[last..start]);
+        filled.push_str(&values.get(name));
+        last = start + name.len() + 2;
+    }
+    filled.push_str(&text[last..]);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn fills_every_string() {
+        let mut component = json!([
+            {"text": "{online}/{max} online", "color": "gold"},
+            {"text": " on {version}", "extra": ["{backend}"]},
+        ]);
+        fill(
+            &mut component,
+            &Placeholders {
+                online: Some(3),
+                max: Some(20),
+                backend: Some("10.0.0.2:25565".to_owned()),
+                version: Some("1.21".to_owned()),
+                player: None,
+            },
+        );
+        assert_eq!(component[0]["text"], "3/20 online");
+        assert_eq!(component[1]["text"], " on 1.21");
+        assert_eq!(component[1]["extra"][0], "10.0.0.2:25565");
+    }
+
+    #[test]
+    fn missing_values_are_empty() {
+        let mut component = json!("Hi {player}!");
+        fill(&mut component, &Placeholders::default());
+        assert_eq!(component, "Hi !");
+    }
+
+    #[test]
+    fn values_are_not_filled_in_again() {
+        let mut component = json!("{player}");
+        fill(
+            &mut component,
+            &Placeholders {
+                player: Some("{online}".to_owned()),
+                ..Placeholders::default()
+            },
+        );
+        assert_eq!(component, "{online}");
+    }
+
+    #[test]
+    fn finds_unknown_placeholders() {
+        assert_eq!(
+            unknown_placeholder(&json!({"text": "{online} {max}"})),
+            None
+        );
+        assert_eq!(unknown_placeholder(&json!("{ not one }")), None);
+        assert_eq!(
+            unknown_placeholder(&json!({"text": "", "extra": ["{onlin}"]})),
+            Some("onlin".to_owned())
+        );
+    }
+}